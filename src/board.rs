@@ -3,33 +3,38 @@
  * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
  */
 
+use crate::square::Square;
+use std::fmt;
+
 /// 棋盘
 #[derive(Debug, Clone)]
 pub struct Board {
     finished: bool,
+    hash: u64,
     map: [[Option<Piece>; 10]; 9],
     piece_count: u32,
-    unmove_records: Vec<UnmoveRecord>,
+    redo_records: Vec<Move>,
+    unmove_records: Vec<MoveRecord>,
     winner: Option<Side>,
 }
 
 /// 一步移动
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Move {
-    pub pos_from: (i32, i32),
-    pub pos_to: (i32, i32),
+    pub pos_from: Square,
+    pub pos_to: Square,
     pub turn_into: Option<Piece>,
 }
 
 /// 棋子
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Piece {
     pub side: Side,
     pub kind: PieceKind,
 }
 
 /// 棋子种类
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PieceKind {
     帥 = 1,
     車 = 2,
@@ -44,26 +49,65 @@ pub enum PieceKind {
 }
 
 /// 阵营
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Side {
     Red,
     Black,
 }
 
-/// 悔棋记录
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct UnmoveRecord {
-    pos_0: (i32, i32),
-    piece_0: Option<Piece>,
-    pos_1: (i32, i32),
-    piece_1: Option<Piece>,
+/// 局面哈希所用的随机数表，按位置和棋子编号索引，在编译期由固定种子生成
+/// 重做或跳转超出了走子历史的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryError;
+
+const ZOBRIST_KEYS: [[u64; 20]; Square::COUNT] = {
+    let mut keys = [[0u64; 20]; Square::COUNT];
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut i = 0;
+    while i < Square::COUNT {
+        let mut j = 0;
+        while j < 20 {
+            // splitmix64
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            keys[i][j] = z ^ (z >> 31);
+            j += 1;
+        }
+        i += 1;
+    }
+    keys
+};
+
+/// 走子记录，同时用于悔棋
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveRecord {
+    /// 所走的一步
+    pub mov: Move,
+    /// 走子前起点上的棋子
+    pub piece_moved: Option<Piece>,
+    /// 被吃掉的棋子
+    pub piece_captured: Option<Piece>,
+    /// 走子前游戏是否已结束
+    finished: bool,
+    /// 走子前的赢家
+    winner: Option<Side>,
+}
+
+/// 历史局面迭代器，见 [`Board::get_history_positions`]
+#[derive(Debug, Clone)]
+pub struct HistoryPositions {
+    board: Option<Board>,
+    moves: std::vec::IntoIter<Move>,
 }
 
 impl Board {
     /// 判断走子是否合法并走子
+    #[allow(clippy::result_unit_err)]
     pub fn apply_move(&mut self, mov: &Move) -> Result<(), ()> {
-        if self.check_move(&mov) {
-            self.apply_move_unchecked(&mov);
+        if self.check_move(mov) {
+            self.apply_move_unchecked(mov);
             Ok(())
         } else {
             Err(())
@@ -74,8 +118,16 @@ impl Board {
     pub fn apply_move_unchecked(&mut self, mov: &Move) {
         let from = mov.pos_from;
         let to = mov.pos_to;
+        // 记录悔棋信息
+        let unmove_record = MoveRecord {
+            mov: *mov,
+            piece_moved: self.get_piece_at(mov.pos_from),
+            piece_captured: self.get_piece_at(mov.pos_to),
+            finished: self.finished,
+            winner: self.winner,
+        };
         // 检查游戏是否结束
-        match self.map[to.0 as usize][to.1 as usize] {
+        match self.get_piece_at(to) {
             Some(piece_killed) => {
                 if piece_killed.kind == PieceKind::帥 {
                     // 更新游戏状态
@@ -89,21 +141,20 @@ impl Board {
                 }
             }
         }
-        // 记录悔棋信息
-        let unmove_record = UnmoveRecord {
-            pos_0: mov.pos_from,
-            piece_0: self.get_piece_at(mov.pos_from),
-            pos_1: mov.pos_to,
-            piece_1: self.get_piece_at(mov.pos_to),
-        };
-        self.unmove_records.push(unmove_record.clone());
+        self.unmove_records.push(unmove_record);
+        // 走的正是可重做的下一步时保留其余重做记录，否则历史已分叉
+        if self.redo_records.last() == Some(mov) {
+            self.redo_records.pop();
+        } else {
+            self.redo_records.clear();
+        }
         // 更新棋子个数
-        if unmove_record.piece_1.is_some() {
+        if unmove_record.piece_captured.is_some() {
             self.piece_count -= 1;
         }
         // 更新棋盘数据
-        self.map[from.0 as usize][from.1 as usize] = None;
-        self.map[to.0 as usize][to.1 as usize] = mov.turn_into;
+        self.set_piece_at(from, None);
+        self.set_piece_at(to, mov.turn_into);
     }
 
     /// 检查走子是否合法
@@ -118,12 +169,12 @@ impl Board {
     }
 
     /// 查询某位置是否被占据
-    pub fn crossing_occupied(&self, pos: (i32, i32)) -> bool {
+    pub fn crossing_occupied(&self, pos: Square) -> bool {
         self.get_piece_at(pos).is_some()
     }
 
     /// 查询某位置是否被某方占据
-    pub fn crossing_occupied_by_side(&self, pos: (i32, i32), side: Side) -> bool {
+    pub fn crossing_occupied_by_side(&self, pos: Square, side: Side) -> bool {
         match self.get_piece_at(pos) {
             Some(piece) => piece.side == side,
             None => false,
@@ -135,6 +186,30 @@ impl Board {
         self.finished
     }
 
+    /// 重做所有撤销的移动
+    pub fn goto_end(&mut self) {
+        while self.redo_move().is_ok() {}
+    }
+
+    /// 跳转到第 `ply` 步之后的局面，只能在已走过或可重做的步数范围内跳转
+    pub fn goto_ply(&mut self, ply: u32) -> Result<(), HistoryError> {
+        if ply > self.get_step_count() + self.get_redo_count() {
+            return Err(HistoryError);
+        }
+        while self.get_step_count() > ply {
+            self.undo_move().map_err(|_| HistoryError)?;
+        }
+        while self.get_step_count() < ply {
+            self.redo_move()?;
+        }
+        Ok(())
+    }
+
+    /// 撤销所有移动，回到起始局面
+    pub fn goto_start(&mut self) {
+        while self.undo_move().is_ok() {}
+    }
+
     /// 查询某一方的帅 / 将是否被威胁
     pub fn general_threatened(&self, side: Side) -> bool {
         for step in self.query_possible_moves_of_side(side.other()) {
@@ -145,20 +220,64 @@ impl Board {
         false
     }
 
+    /// 获取某方被吃掉的所有棋子，按被吃的先后顺序
+    pub fn get_captured_pieces(&self, side: Side) -> impl Iterator<Item = Piece> + '_ {
+        self.unmove_records
+            .iter()
+            .filter_map(|record| record.piece_captured)
+            .filter(move |piece| piece.side == side)
+    }
+
     /// 获取记录棋子信息的二维数组
     pub fn get_board(&self) -> &[[Option<Piece>; 10]; 9] {
         &self.map
     }
 
+    /// 获取从开局（或自定义局面）到当前的所有局面，包括起始局面和当前局面
+    pub fn get_history_positions(&self) -> HistoryPositions {
+        let mut board = self.clone();
+        board.goto_start();
+        HistoryPositions {
+            board: Some(board),
+            moves: self
+                .unmove_records
+                .iter()
+                .map(|record| record.mov)
+                .collect::<Vec<Move>>()
+                .into_iter(),
+        }
+    }
+
+    /// 获取局面哈希
+    ///
+    /// 只与棋盘上各位置的棋子有关，不包含走子方和历史信息。
+    pub fn get_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// 获取上一步走子记录
+    pub fn get_last_move(&self) -> Option<&MoveRecord> {
+        self.unmove_records.last()
+    }
+
+    /// 获取走子历史
+    pub fn get_move_history(&self) -> &[MoveRecord] {
+        &self.unmove_records
+    }
+
     /// 获取目前步数
     pub fn get_move_count(&self) -> u32 {
         self.unmove_records.len() as u32
     }
 
     /// 获取某位置的棋子
-    pub fn get_piece_at(&self, pos: (i32, i32)) -> Option<Piece> {
-        debug_assert!(pos.0 >= 0 && pos.0 < 9 && pos.1 >= 0 && pos.1 < 10);
-        self.map[pos.0 as usize][pos.1 as usize]
+    pub fn get_piece_at(&self, pos: Square) -> Option<Piece> {
+        self.map[pos.file() as usize][pos.rank() as usize]
+    }
+
+    /// 获取可重做的步数
+    pub fn get_redo_count(&self) -> u32 {
+        self.redo_records.len() as u32
     }
 
     /// 获取整个棋盘棋子总个数
@@ -166,6 +285,15 @@ impl Board {
         self.piece_count
     }
 
+    /// 获取距离上一次吃子的步数，从未吃子时为总步数
+    pub fn get_ply_since_capture(&self) -> u32 {
+        self.unmove_records
+            .iter()
+            .rev()
+            .take_while(|record| record.piece_captured.is_none())
+            .count() as u32
+    }
+
     /// 获取当前步数
     pub fn get_step_count(&self) -> u32 {
         self.unmove_records.len() as u32
@@ -176,19 +304,29 @@ impl Board {
         self.winner
     }
 
+    /// 查询走子是否为最后四步形成循环后的第五步不变着，按规则三判负
+    pub fn is_repetition(&self, mov: &Move) -> bool {
+        if self.looped() {
+            let record = &self.unmove_records[self.unmove_records.len() - 4];
+            mov.pos_from == record.mov.pos_from || mov.pos_to == record.mov.pos_to
+        } else {
+            false
+        }
+    }
+
     /// 检查最后四步是否形成循环
     pub fn looped(&self) -> bool {
         let records = &self.unmove_records;
         if records.len() >= 4 {
             let len = records.len();
-            records[len - 4].piece_1.is_none()
-                && records[len - 3].piece_1.is_none()
-                && records[len - 2].piece_1.is_none()
-                && records[len - 1].piece_1.is_none()
-                && records[len - 4].pos_0 == records[len - 2].pos_1
-                && records[len - 4].pos_1 == records[len - 2].pos_0
-                && records[len - 3].pos_0 == records[len - 1].pos_1
-                && records[len - 3].pos_1 == records[len - 1].pos_0
+            records[len - 4].piece_captured.is_none()
+                && records[len - 3].piece_captured.is_none()
+                && records[len - 2].piece_captured.is_none()
+                && records[len - 1].piece_captured.is_none()
+                && records[len - 4].mov.pos_from == records[len - 2].mov.pos_to
+                && records[len - 4].mov.pos_to == records[len - 2].mov.pos_from
+                && records[len - 3].mov.pos_from == records[len - 1].mov.pos_to
+                && records[len - 3].mov.pos_to == records[len - 1].mov.pos_from
         } else {
             false
         }
@@ -221,64 +359,53 @@ impl Board {
         map[7][2] = Some(Piece::new(PieceKind::炮, Side::Red));
         map[1][7] = Some(Piece::new(PieceKind::炮, Side::Black));
         map[7][7] = Some(Piece::new(PieceKind::炮, Side::Black));
-        Board {
-            finished: false,
-            map,
-            piece_count: 32,
-            unmove_records: Vec::new(),
-            winner: None,
-        }
+        Board::new_custom(map)
     }
 
     /// 构造自定义棋盘
     pub fn new_custom(map: [[Option<Piece>; 10]; 9]) -> Board {
         let ret = Board {
             finished: false,
+            // 还未计算哈希，先设为 0
+            hash: 0,
             map,
             // 还未统计棋子个数，先设为 0
             piece_count: 0,
+            redo_records: Vec::new(),
             unmove_records: Vec::new(),
             winner: None,
         };
-        // 这个时候再更新棋子个数
+        // 这个时候再更新棋子个数和哈希
         // 我真聪明
         Board {
-            piece_count: ret.query_piece_count_between((0, 0), (8, 9)),
+            hash: Square::all()
+                .filter_map(|pos| Some(ZOBRIST_KEYS[pos.index()][ret.get_piece_at(pos)?.index()]))
+                .fold(0, |hash, key| hash ^ key),
+            piece_count: Square::all()
+                .filter(|&pos| ret.crossing_occupied(pos))
+                .count() as u32,
             ..ret
         }
     }
 
-    /// 判断位置是否在棋盘内
-    pub fn position_within_board(pos: (i32, i32)) -> bool {
-        Board::position_within_range(pos, (0, 0), (8, 9))
-    }
-
-    /// 判断位置是否在某范围内
-    pub fn position_within_range(pos: (i32, i32), bound1: (i32, i32), bound2: (i32, i32)) -> bool {
-        (pos.0 - bound1.0) * (pos.0 - bound2.0) <= 0 && (pos.1 - bound1.1) * (pos.1 - bound2.1) <= 0
-    }
-
     /// 查询范围内棋子数
-    pub fn query_piece_count_between(&self, pos1: (i32, i32), pos2: (i32, i32)) -> u32 {
+    pub fn query_piece_count_between(&self, pos1: Square, pos2: Square) -> u32 {
         // 确定左右边界
-        let (left, right) = if pos1.0 < pos2.0 {
-            (pos1.0, pos2.0)
+        let (left, right) = if pos1.file() < pos2.file() {
+            (pos1.file(), pos2.file())
         } else {
-            (pos2.0, pos1.0)
+            (pos2.file(), pos1.file())
         };
         // 确定上下边界
-        let (down, up) = if pos1.1 < pos2.1 {
-            (pos1.1, pos2.1)
+        let (down, up) = if pos1.rank() < pos2.rank() {
+            (pos1.rank(), pos2.rank())
         } else {
-            (pos2.1, pos1.1)
+            (pos2.rank(), pos1.rank())
         };
-        // 检查是否在棋盘范围内
-        debug_assert!(left >= 0 && right < 9);
-        debug_assert!(down >= 0 && up < 10);
         let mut ret = 0u32;
         for x in left..right + 1 {
             for y in down..up + 1 {
-                if self.get_piece_at((x, y)).is_some() {
+                if self.map[x as usize][y as usize].is_some() {
                     ret += 1;
                 }
             }
@@ -287,113 +414,102 @@ impl Board {
     }
 
     /// 查询某位置的所有走法
-    pub fn query_possible_moves_from(&self, from: (i32, i32)) -> impl Iterator<Item = Move> {
+    pub fn query_possible_moves_from(&self, from: Square) -> impl Iterator<Item = Move> {
         let mut ret = Vec::<Move>::new();
-        match self.get_piece_at(from) {
-            Some(piece) => {
-                let kind = piece.kind;
-                let side = piece.side;
-                match kind {
-                    PieceKind::帥 => {
-                        // 九宫格边界
-                        let (left_down, right_up) = match side {
-                            Side::Red => ((3, 0), (5, 2)),
-                            Side::Black => ((3, 7), (5, 9)),
-                        };
-                        // 前后左右
-                        for offset in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
-                            let to = (from.0 + offset.0, from.1 + offset.1);
-                            if Board::position_within_range(to, left_down, right_up)
-                                && !self.crossing_occupied_by_side(to, side)
-                            {
+        if let Some(piece) = self.get_piece_at(from) {
+            let kind = piece.kind;
+            let side = piece.side;
+            match kind {
+                PieceKind::帥 => {
+                    // 前后左右
+                    for offset in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                        if let Some(to) = from.offset(offset.0, offset.1) {
+                            if to.in_palace(side) && !self.crossing_occupied_by_side(to, side) {
                                 ret.push(Move::new(from, to, Some(piece)));
                             }
                         }
-                        // 跳过去吃对方的帅
-                        for y in [0, 1, 2, 7, 8, 9] {
-                            let to = (from.0, y);
-                            if self.get_piece_at(to) == Some(Piece::new(kind, side.other())) {
-                                if self.query_piece_count_between(from, to) == 2 {
-                                    ret.push(Move::new(from, to, Some(piece)));
-                                }
-                            }
+                    }
+                    // 跳过去吃对方的帅
+                    for to in [0, 1, 2, 7, 8, 9]
+                        .into_iter()
+                        .filter_map(|y| Square::new(from.file(), y))
+                    {
+                        if self.get_piece_at(to) == Some(Piece::new(kind, side.other()))
+                            && self.query_piece_count_between(from, to) == 2
+                        {
+                            ret.push(Move::new(from, to, Some(piece)));
                         }
                     }
-                    PieceKind::車 => {
-                        let positions_of_same_line = (0..9).map(|x| (x, from.1));
-                        let positions_of_same_col = (0..10).map(|y| (from.0, y));
-                        for to in positions_of_same_line.chain(positions_of_same_col) {
-                            if self.crossing_occupied_by_side(to, side) {
-                                continue;
-                            }
-                            if self.crossing_occupied_by_side(to, side.other()) {
-                                if self.query_piece_count_between(from, to) == 2 {
-                                    ret.push(Move::new(from, to, Some(piece)));
-                                }
-                            } else {
-                                if self.query_piece_count_between(from, to) == 1 {
-                                    ret.push(Move::new(from, to, Some(piece)));
-                                }
+                }
+                PieceKind::車 => {
+                    let positions_of_same_line = (0..9).filter_map(|x| Square::new(x, from.rank()));
+                    let positions_of_same_col = (0..10).filter_map(|y| Square::new(from.file(), y));
+                    for to in positions_of_same_line.chain(positions_of_same_col) {
+                        if self.crossing_occupied_by_side(to, side) {
+                            continue;
+                        }
+                        if self.crossing_occupied_by_side(to, side.other()) {
+                            if self.query_piece_count_between(from, to) == 2 {
+                                ret.push(Move::new(from, to, Some(piece)));
                             }
+                        } else if self.query_piece_count_between(from, to) == 1 {
+                            ret.push(Move::new(from, to, Some(piece)));
                         }
                     }
-                    PieceKind::馬 => {
-                        for (offset, checkpoint_offset) in [
-                            ((2, 1), (1, 0)),
-                            ((2, -1), (1, 0)),
-                            ((-2, 1), (-1, 0)),
-                            ((-2, -1), (-1, 0)),
-                            ((1, 2), (0, 1)),
-                            ((1, -2), (0, -1)),
-                            ((-1, 2), (0, 1)),
-                            ((-1, -2), (0, -1)),
-                        ] {
-                            let to = (from.0 + offset.0, from.1 + offset.1);
-                            let checkpoint =
-                                (from.0 + checkpoint_offset.0, from.1 + checkpoint_offset.1);
-                            if Board::position_within_board(to)
-                                && !self.crossing_occupied_by_side(to, side)
+                }
+                PieceKind::馬 => {
+                    for (offset, checkpoint_offset) in [
+                        ((2, 1), (1, 0)),
+                        ((2, -1), (1, 0)),
+                        ((-2, 1), (-1, 0)),
+                        ((-2, -1), (-1, 0)),
+                        ((1, 2), (0, 1)),
+                        ((1, -2), (0, -1)),
+                        ((-1, 2), (0, 1)),
+                        ((-1, -2), (0, -1)),
+                    ] {
+                        if let (Some(to), Some(checkpoint)) = (
+                            from.offset(offset.0, offset.1),
+                            from.offset(checkpoint_offset.0, checkpoint_offset.1),
+                        ) {
+                            if !self.crossing_occupied_by_side(to, side)
                                 && !self.crossing_occupied(checkpoint)
                             {
                                 ret.push(Move::new(from, to, Some(piece)));
                             }
                         }
                     }
-                    PieceKind::炮 => {
-                        let positions_of_same_line = (0..9).map(|x| (x, from.1));
-                        let positions_of_same_col = (0..10).map(|y| (from.0, y));
-                        for to in positions_of_same_line.chain(positions_of_same_col) {
-                            if self.crossing_occupied_by_side(to, side) {
-                                continue;
-                            }
-                            if self.crossing_occupied_by_side(to, side.other()) {
-                                if self.query_piece_count_between(from, to) == 3 {
-                                    ret.push(Move::new(from, to, Some(piece)));
-                                }
-                            } else {
-                                if self.query_piece_count_between(from, to) == 1 {
-                                    ret.push(Move::new(from, to, Some(piece)));
-                                }
+                }
+                PieceKind::炮 => {
+                    let positions_of_same_line = (0..9).filter_map(|x| Square::new(x, from.rank()));
+                    let positions_of_same_col = (0..10).filter_map(|y| Square::new(from.file(), y));
+                    for to in positions_of_same_line.chain(positions_of_same_col) {
+                        if self.crossing_occupied_by_side(to, side) {
+                            continue;
+                        }
+                        if self.crossing_occupied_by_side(to, side.other()) {
+                            if self.query_piece_count_between(from, to) == 3 {
+                                ret.push(Move::new(from, to, Some(piece)));
                             }
+                        } else if self.query_piece_count_between(from, to) == 1 {
+                            ret.push(Move::new(from, to, Some(piece)));
                         }
                     }
-                    PieceKind::相 => {
-                        // 本方边界
-                        let (left_down, right_up) = match side {
-                            Side::Red => ((0, 0), (8, 4)),
-                            Side::Black => ((0, 5), (8, 9)),
-                        };
-                        // 田字
-                        for (offset, checkpoint_offset) in [
-                            ((2, 2), (1, 1)),
-                            ((2, -2), (1, -1)),
-                            ((-2, 2), (-1, 1)),
-                            ((-2, -2), (-1, -1)),
-                        ] {
-                            let to = (from.0 + offset.0, from.1 + offset.1);
-                            let checkpoint =
-                                (from.0 + checkpoint_offset.0, from.1 + checkpoint_offset.1);
-                            if Board::position_within_range(to, left_down, right_up)
+                }
+                PieceKind::相 => {
+                    // 田字
+                    for (offset, checkpoint_offset) in [
+                        ((2, 2), (1, 1)),
+                        ((2, -2), (1, -1)),
+                        ((-2, 2), (-1, 1)),
+                        ((-2, -2), (-1, -1)),
+                    ] {
+                        if let (Some(to), Some(checkpoint)) = (
+                            from.offset(offset.0, offset.1),
+                            from.offset(checkpoint_offset.0, checkpoint_offset.1),
+                        ) {
+                            // 不能过河
+                            if to.on_own_side(side)
                                 && !self.crossing_occupied_by_side(to, side)
                                 && !self.crossing_occupied(checkpoint)
                             {
@@ -401,35 +517,25 @@ impl Board {
                             }
                         }
                     }
-                    PieceKind::仕 => {
-                        // 九宫格边界
-                        let (left_down, right_up) = match side {
-                            Side::Red => ((3, 0), (5, 2)),
-                            Side::Black => ((3, 7), (5, 9)),
-                        };
-                        // 对角
-                        for offset in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
-                            let to = (from.0 + offset.0, from.1 + offset.1);
-                            if Board::position_within_range(to, left_down, right_up)
-                                && !self.crossing_occupied_by_side(to, side)
-                            {
+                }
+                PieceKind::仕 => {
+                    // 对角
+                    for offset in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                        if let Some(to) = from.offset(offset.0, offset.1) {
+                            if to.in_palace(side) && !self.crossing_occupied_by_side(to, side) {
                                 ret.push(Move::new(from, to, Some(piece)));
                             }
                         }
                     }
-                    PieceKind::中兵 | PieceKind::庶兵 => {
-                        let to = match side {
-                            Side::Red => (from.0, from.1 + 1),
-                            Side::Black => (from.0, from.1 - 1),
-                        };
-                        let opposite_river_side = match side {
-                            Side::Red => 5,
-                            Side::Black => 4,
-                        };
-                        if Board::position_within_board(to)
-                            && !self.crossing_occupied_by_side(to, side)
-                        {
-                            if to.1 == opposite_river_side {
+                }
+                PieceKind::中兵 | PieceKind::庶兵 => {
+                    let forward = match side {
+                        Side::Red => 1,
+                        Side::Black => -1,
+                    };
+                    if let Some(to) = from.offset(0, forward) {
+                        if !self.crossing_occupied_by_side(to, side) {
+                            if to.crossed_river(side) {
                                 ret.push(Move::new(
                                     from,
                                     to,
@@ -440,21 +546,20 @@ impl Board {
                             }
                         }
                     }
-                    PieceKind::濟兵 => {
-                        let offsets = match side {
-                            Side::Red => [(1, 0), (-1, 0), (0, 1)],
-                            Side::Black => [(1, 0), (-1, 0), (0, -1)],
-                        };
-                        let opposite_bottom_line = match side {
-                            Side::Red => 9,
-                            Side::Black => 0,
-                        };
-                        for offset in offsets {
-                            let to = (from.0 + offset.0, from.1 + offset.1);
-                            if Board::position_within_board(to)
-                                && !self.crossing_occupied_by_side(to, side)
-                            {
-                                if to.1 == opposite_bottom_line {
+                }
+                PieceKind::濟兵 => {
+                    let offsets = match side {
+                        Side::Red => [(1, 0), (-1, 0), (0, 1)],
+                        Side::Black => [(1, 0), (-1, 0), (0, -1)],
+                    };
+                    let opposite_bottom_line = match side {
+                        Side::Red => 9,
+                        Side::Black => 0,
+                    };
+                    for offset in offsets {
+                        if let Some(to) = from.offset(offset.0, offset.1) {
+                            if !self.crossing_occupied_by_side(to, side) {
+                                if to.rank() == opposite_bottom_line {
                                     ret.push(Move::new(
                                         from,
                                         to,
@@ -466,25 +571,22 @@ impl Board {
                             }
                         }
                     }
-                    PieceKind::底兵 => {
-                        for to in [(from.0 + 1, from.1), (from.0 - 1, from.1)] {
-                            if Board::position_within_board(to)
-                                && !self.crossing_occupied_by_side(to, side)
-                            {
-                                ret.push(Move::new(from, to, Some(piece)));
-                            }
+                }
+                PieceKind::底兵 => {
+                    for to in [from.offset(1, 0), from.offset(-1, 0)]
+                        .into_iter()
+                        .flatten()
+                    {
+                        if !self.crossing_occupied_by_side(to, side) {
+                            ret.push(Move::new(from, to, Some(piece)));
                         }
                     }
                 }
             }
-            None => {}
         }
         if self.looped() {
             ret.into_iter()
-                .filter(|x| {
-                    let record = &self.unmove_records[self.unmove_records.len() - 4];
-                    x.pos_from != record.pos_0 && x.pos_to != record.pos_1
-                })
+                .filter(|x| !self.is_repetition(x))
                 .collect::<Vec<Move>>()
                 .into_iter()
         } else {
@@ -495,18 +597,90 @@ impl Board {
     /// 查询某方的所有走法
     pub fn query_possible_moves_of_side(&self, side: Side) -> impl Iterator<Item = Move> {
         let mut ret = Vec::new();
-        for x in 0..9 {
-            for y in 0..10 {
-                let from = (x, y);
-                if self.crossing_occupied_by_side(from, side) {
-                    ret.push(self.query_possible_moves_from(from));
-                }
+        for from in Square::all() {
+            if self.crossing_occupied_by_side(from, side) {
+                ret.push(self.query_possible_moves_from(from));
             }
         }
         ret.into_iter().flatten()
     }
 
+    /// 重做上一步撤销的移动
+    pub fn redo_move(&mut self) -> Result<(), HistoryError> {
+        match self.redo_records.last() {
+            Some(&mov) => {
+                self.apply_move_unchecked(&mov);
+                Ok(())
+            }
+            None => Err(HistoryError),
+        }
+    }
+
+    /// 静态交换评估
+    ///
+    /// 假设双方轮流以价值最低的棋子在目标位置上吃子，且任何一方都可以在对自己有利时停止，
+    /// 计算走子方最终的子力得失。炮架的出现与消失、对将飞吃都按实际走法处理。
+    /// 棋子价值由调用者通过 `piece_value` 提供。只复制棋盘而不复制走子历史，
+    /// 每次吃子只为按走法形状可能到达目标位置的棋子生成走法。
+    pub fn see<F: Fn(Piece) -> i32>(&self, mov: &Move, piece_value: F) -> i32 {
+        let side = match self.get_piece_at(mov.pos_from) {
+            Some(piece) => piece.side,
+            None => return 0,
+        };
+        let target = mov.pos_to;
+        let mut playground = Board::new_custom(self.map);
+        // gains[i] 为第 i 次吃子后，吃子方在假设对方继续兑子时的得失
+        let mut gains = vec![self.get_piece_at(target).map_or(0, &piece_value)];
+        playground.apply_move_unchecked(mov);
+        let mut current_side = side.other();
+        while !playground.game_finished() {
+            // 找出价值最低的吃子者
+            let attacker = Square::all()
+                .filter(|&from| {
+                    playground.get_piece_at(from).is_some_and(|piece| {
+                        piece.side == current_side && piece.may_reach(from, target)
+                    })
+                })
+                .flat_map(|from| playground.query_possible_moves_from(from))
+                .filter(|step| step.pos_to == target)
+                .min_by_key(|step| {
+                    playground
+                        .get_piece_at(step.pos_from)
+                        .map_or(0, &piece_value)
+                });
+            match attacker {
+                Some(step) => {
+                    let captured = playground.get_piece_at(target).map_or(0, &piece_value);
+                    gains.push(captured - gains[gains.len() - 1]);
+                    playground.apply_move_unchecked(&step);
+                    current_side = current_side.other();
+                }
+                None => break,
+            }
+        }
+        // 倒推，每一方都可以选择不再继续吃子
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -(-*previous).max(last);
+        }
+        gains[0]
+    }
+
+    /// 设置某位置的棋子
+    fn set_piece_at(&mut self, pos: Square, piece: Option<Piece>) {
+        // 更新局面哈希
+        if let Some(old_piece) = self.get_piece_at(pos) {
+            self.hash ^= ZOBRIST_KEYS[pos.index()][old_piece.index()];
+        }
+        if let Some(new_piece) = piece {
+            self.hash ^= ZOBRIST_KEYS[pos.index()][new_piece.index()];
+        }
+        self.map[pos.file() as usize][pos.rank() as usize] = piece;
+    }
+
     /// 撤销上一步移动
+    #[allow(clippy::result_unit_err)]
     pub fn undo_move(&mut self) -> Result<(), ()> {
         if self.unmove_records.is_empty() {
             Err(())
@@ -514,23 +688,56 @@ impl Board {
             // 获取记录
             let record = self.unmove_records.pop().unwrap();
             // 恢复棋盘
-            self.map[record.pos_0.0 as usize][record.pos_0.1 as usize] = record.piece_0;
-            self.map[record.pos_1.0 as usize][record.pos_1.1 as usize] = record.piece_1;
+            self.set_piece_at(record.mov.pos_from, record.piece_moved);
+            self.set_piece_at(record.mov.pos_to, record.piece_captured);
             // 恢复游戏状态
-            self.finished = false;
-            self.winner = None;
+            self.finished = record.finished;
+            self.winner = record.winner;
             // 恢复棋子个数
-            if record.piece_1.is_some() {
+            if record.piece_captured.is_some() {
                 self.piece_count += 1;
             }
+            // 记录重做信息
+            self.redo_records.push(record.mov);
             Ok(())
         }
     }
 }
 
+impl fmt::Display for HistoryError {
+    /// 输出错误信息
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("超出走子历史的范围")
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+impl Default for Board {
+    /// 按照默认开局构建棋盘
+    fn default() -> Board {
+        Board::new()
+    }
+}
+
+impl Iterator for HistoryPositions {
+    type Item = Board;
+
+    /// 获取下一个历史局面
+    fn next(&mut self) -> Option<Board> {
+        let board = self.board.as_mut()?;
+        let ret = board.clone();
+        match self.moves.next() {
+            Some(mov) => board.apply_move_unchecked(&mov),
+            None => self.board = None,
+        }
+        Some(ret)
+    }
+}
+
 impl Move {
     /// 构造
-    pub fn new(pos_from: (i32, i32), pos_to: (i32, i32), turn_into: Option<Piece>) -> Move {
+    pub fn new(pos_from: Square, pos_to: Square, turn_into: Option<Piece>) -> Move {
         Move {
             pos_from,
             pos_to,
@@ -540,6 +747,28 @@ impl Move {
 }
 
 impl Piece {
+    /// 获取 0 到 19 的棋子编号
+    fn index(&self) -> usize {
+        (self.kind as usize - 1) * 2 + self.side as usize
+    }
+
+    /// 查询按走法的形状能否从起点走到终点，不考虑阻挡、九宫和河界，用于快速排除
+    fn may_reach(&self, from: Square, to: Square) -> bool {
+        let file_distance = (from.file() - to.file()).abs();
+        let rank_distance = (from.rank() - to.rank()).abs();
+        match self.kind {
+            // 对将飞吃时在同一列
+            PieceKind::帥 => file_distance + rank_distance == 1 || file_distance == 0,
+            PieceKind::車 | PieceKind::炮 => file_distance == 0 || rank_distance == 0,
+            PieceKind::馬 => file_distance * rank_distance == 2,
+            PieceKind::相 => file_distance == 2 && rank_distance == 2,
+            PieceKind::仕 => file_distance == 1 && rank_distance == 1,
+            PieceKind::中兵 | PieceKind::濟兵 | PieceKind::庶兵 | PieceKind::底兵 => {
+                file_distance + rank_distance == 1
+            }
+        }
+    }
+
     /// 构造
     pub fn new(kind: PieceKind, side: Side) -> Piece {
         Piece { kind, side }
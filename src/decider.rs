@@ -4,180 +4,1320 @@
  */
 
 use crate::{
-    board::{Board, Move, Side},
+    board::{Board, Move, Piece, PieceKind, Side},
     evaluator::Evaluator,
+    game::GameOutcome,
+    score::Score,
+    search::{
+        Clock, ProgressCallback, SearchConfig, SearchLimits, SearchResult, StopSignal, TimeManager,
+    },
+    square::Square,
+    transposition::{Bound, TableEntry, TranspositionTable},
 };
-use rand::prelude::*;
+use rand::{prelude::*, rngs::StdRng};
+use std::cmp::Reverse;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 /// 走子决定器接口
+///
+/// 决定器可以在两步之间保存状态。[`crate::game::Game`] 在每局开始时调用 [`Decider::new_game`]，
+/// 对方每走一步调用 [`Decider::opponent_moved`]，结束时调用 [`Decider::game_over`]。
 pub trait Decider {
-    /// 作出走子决定
-    fn make_decision(&self, board: &Board, side: Side) -> Option<Move>;
+    /// 作出的决定不合法而被 [`crate::game::Game`] 拒绝，随后会被要求重新决定
+    fn decision_rejected(&mut self, _board: &Board, _decision: &Decision) {}
+
+    /// 对方走子时提出和棋，下一次决定时返回 [`Decision::AcceptDraw`] 即接受，否则视为拒绝
+    fn draw_offered(&mut self, _board: &Board) {}
+
+    /// 一局结束，`outcome` 与 [`crate::game::Game::go`] 的返回值相同
+    fn game_over(&mut self, _outcome: &GameOutcome) {}
+
+    /// 作出决定，可以走子、认输或提出、接受、要求和棋
+    fn make_decision(&mut self, board: &Board, side: Side) -> Decision;
+
+    /// 新的一局开始，`side` 为该决定器执的一方
+    fn new_game(&mut self, _board: &Board, _side: Side) {}
+
+    /// 对方走了一步，`board` 为走子之后的局面
+    fn opponent_moved(&mut self, _board: &Board, _mov: &Move) {}
+
+    /// 作出走子决定，并返回分数、深度等详细信息
+    ///
+    /// 默认只填写最佳走法和用时，不搜索的决定器无需实现。
+    fn search(&mut self, board: &Board, side: Side) -> SearchResult {
+        let start = Instant::now();
+        let best_move = self.make_decision(board, side).get_move();
+        SearchResult {
+            best_move,
+            elapsed: start.elapsed(),
+            principal_variation: best_move.into_iter().collect(),
+            ..SearchResult::default()
+        }
+    }
+
+    /// 多变例分析，返回最好的若干个候选走法及其分数和主要变例，按优劣排列
+    ///
+    /// 默认只返回 [`Decider::search`] 的结果。
+    fn search_multi_pv(
+        &mut self,
+        board: &Board,
+        side: Side,
+        line_count: usize,
+    ) -> Vec<SearchResult> {
+        let mut ret = vec![self.search(board, side)];
+        ret.truncate(line_count);
+        ret
+    }
+
+    /// 设置棋钟，`None` 表示不限时，不搜索的决定器忽略该设置
+    ///
+    /// 只替换搜索限制中的棋钟，深度、节点数等其余限制不变。
+    fn set_clock(&mut self, _clock: Option<Clock>) {}
+
+    /// 设置搜索限制，不搜索的决定器忽略该设置
+    fn set_limits(&mut self, _limits: SearchLimits) {}
+
+    /// 设置是否在对方思考时后台思考，不支持的决定器忽略该设置
+    fn set_pondering(&mut self, _pondering: bool) {}
+
+    /// 设置进度回调，不搜索的决定器忽略该设置
+    fn set_progress_callback(&mut self, _callback: ProgressCallback) {}
+
+    /// 设置向对局观察者报告搜索信息的回调，`None` 表示不报告，不搜索的决定器忽略该设置
+    ///
+    /// 由 [`crate::game::Game`] 在有观察者时设置、对局结束后清除，
+    /// 与 [`Decider::set_progress_callback`] 设置的回调互不影响，两者都在同样的时机调用。
+    fn set_search_info_callback(&mut self, _callback: Option<ProgressCallback>) {}
+
+    /// 设置随机数种子，设置后同样的局面序列得到同样的决定，不使用随机数的决定器忽略该设置
+    ///
+    /// 未设置时所有决定器都使用同一个固定的默认种子，因此默认构造的决定器同样可以重现。
+    /// 受时间限制或使用多个线程的搜索仍然可能得到不同的决定。
+    fn set_seed(&mut self, _seed: u64) {}
+
+    /// 设置停止信号，不搜索的决定器忽略该设置
+    fn set_stop_signal(&mut self, _signal: StopSignal) {}
+}
+
+/// 决定器的决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// 接受对方上一步提出的和棋
+    AcceptDraw,
+    /// 按自然限着要求判和
+    ClaimDraw,
+    /// 走子
+    Move(Move),
+    /// 走子并提出和棋
+    OfferDraw(Move),
+    /// 认输
+    Resign,
 }
 
 /// 最大-最小算法决定器实现
-#[derive(Debug, Clone)]
+///
+/// 未设置随机数种子时使用固定的种子，因此单线程且不受时间限制的搜索结果可以重现。
+#[derive(Debug)]
 pub struct MaxMinDecider<E: Evaluator> {
-    evaluator: E,
-    max_node_count: u32,
+    config: SearchConfig,
+    evaluator: Arc<E>,
+    limits: SearchLimits,
+    ponder_job: Option<PonderJob>,
+    pondering: bool,
+    progress_callback: Option<ProgressCallback>,
+    rng: DeciderRng,
+    search_info_callback: Option<ProgressCallback>,
+    stop_signal: StopSignal,
+    thread_count: usize,
+    transposition_table: Arc<TranspositionTable>,
+}
+
+/// 正在进行的后台思考
+#[derive(Debug)]
+struct PonderJob {
+    /// 预计对方走子后轮到的一方
+    side: Side,
+    /// 预计对方走子后的局面对应的置换表键
+    expected_key: u64,
+    /// 对方是否已经按预计走子
+    hit: bool,
+    /// 开始后台思考的时刻
+    start: Instant,
+    /// 停止后台思考的信号
+    stop_signal: StopSignal,
+    /// 后台思考的线程
+    handle: JoinHandle<Option<SearchResult>>,
 }
 
 /// 随机走子决定器实现
-#[derive(Debug, Clone)]
-pub struct RandomDecider {}
+#[derive(Debug, Clone, Default)]
+pub struct RandomDecider {
+    rng: DeciderRng,
+}
 
-impl<E: Evaluator> MaxMinDecider<E> {
-    /// 构造
-    pub fn new(evaluator: E, max_node_count: u32) -> MaxMinDecider<E> {
-        Self {
-            evaluator,
-            max_node_count,
+/// 决定器使用的随机数发生器，可以设置种子以重现对局
+///
+/// 可以在多个线程间共享，每次决定从中派生一个独立的随机数发生器。
+pub(crate) struct DeciderRng(Mutex<StdRng>);
+
+/// 增量剪枝时在被吃棋子的子力价值之外额外保留的余量
+const DELTA_MARGIN: i32 = 20;
+
+/// 最大搜索深度
+const MAX_DEPTH: u32 = 128;
+
+/// 每搜索多少个节点检查一次时间
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// 置换表默认大小，单位为 MiB
+const DEFAULT_TRANSPOSITION_TABLE_SIZE: usize = 16;
+
+/// 使用空着剪枝的最小剩余深度
+const NULL_MOVE_MIN_DEPTH: u32 = 3;
+
+/// 使用剃刀剪枝和前沿节点剪枝的最大剩余深度
+const FRONTIER_MAX_DEPTH: u32 = 2;
+
+/// 使用后期走法衰减的最小剩余深度
+const LATE_MOVE_MIN_DEPTH: u32 = 3;
+
+/// 从第几个走法开始使用后期走法衰减
+const LATE_MOVE_MIN_INDEX: usize = 3;
+
+/// 猜中后等待后台思考时检查其是否结束的间隔
+const PONDER_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// 每层记录的杀手走法个数
+const KILLER_COUNT: usize = 2;
+
+/// 未设置种子时各决定器使用的随机数种子
+const DEFAULT_SEED: u64 = 0;
+
+/// 一次搜索过程中的状态
+struct SearchState {
+    /// 已搜索的节点数
+    node_count: u64,
+    /// 最大节点数
+    max_node_count: Option<u64>,
+    /// 时间管理器
+    time_manager: TimeManager,
+    /// 停止信号
+    stop_signal: StopSignal,
+    /// 根节点已搜完走法中的最佳分数
+    root_best: Score,
+    /// 用于在同分时随机选择的随机数发生器
+    rng: StdRng,
+    /// 每层的杀手走法，即在同一层引起过截断的非吃子走法，新的在前
+    killers: Vec<[Option<Move>; KILLER_COUNT]>,
+    /// 历史表，按走子方、起点和终点累计非吃子走法引起截断的次数，以深度的平方加权
+    history: Vec<u32>,
+    /// 应着表，按对方上一步的起点和终点记录引起截断的非吃子应着
+    countermoves: Vec<Option<Move>>,
+    /// 发生截断的节点数
+    cutoff_count: u64,
+    /// 第一个走法即引起截断的节点数
+    first_move_cutoff_count: u64,
+    /// 最近一次空着所在的层数，用于避免连续空着
+    null_move_ply: Option<u32>,
+}
+
+impl SearchState {
+    /// 获取对方上一步对应的应着
+    fn get_countermove(&self, board: &Board) -> Option<Move> {
+        let last = board.get_last_move()?;
+        self.countermoves[move_index(&last.mov)]
+    }
+
+    /// 获取非吃子走法的历史分数
+    fn get_history(&self, side: Side, step: &Move) -> u32 {
+        self.history[side as usize * Square::COUNT * Square::COUNT + move_index(step)]
+    }
+
+    /// 获取某层的杀手走法
+    fn get_killers(&self, ply: u32) -> [Option<Move>; KILLER_COUNT] {
+        self.killers
+            .get(ply as usize)
+            .copied()
+            .unwrap_or([None; KILLER_COUNT])
+    }
+
+    /// 按搜索限制开始一次搜索
+    fn new(limits: &SearchLimits, stop_signal: &StopSignal, rng: StdRng) -> SearchState {
+        SearchState {
+            node_count: 0,
+            max_node_count: limits.nodes,
+            time_manager: TimeManager::new(limits),
+            stop_signal: stop_signal.clone(),
+            root_best: -Score::INFINITY,
+            rng,
+            killers: vec![[None; KILLER_COUNT]; MAX_DEPTH as usize + 1],
+            history: vec![0; 2 * Square::COUNT * Square::COUNT],
+            countermoves: vec![None; Square::COUNT * Square::COUNT],
+            cutoff_count: 0,
+            first_move_cutoff_count: 0,
+            null_move_ply: None,
+        }
+    }
+
+    /// 记录第 `index` 个走法引起的截断，非吃子走法计入杀手走法、历史表和应着表
+    ///
+    /// 需在走法撤销后调用。
+    fn record_cutoff(
+        &mut self,
+        board: &Board,
+        side: Side,
+        step: &Move,
+        depth: u32,
+        ply: u32,
+        index: usize,
+    ) {
+        self.cutoff_count += 1;
+        if index == 0 {
+            self.first_move_cutoff_count += 1;
+        }
+        if board.get_piece_at(step.pos_to).is_some() {
+            return;
+        }
+        if let Some(killers) = self.killers.get_mut(ply as usize) {
+            if killers[0] != Some(*step) {
+                killers.rotate_right(1);
+                killers[0] = Some(*step);
+            }
+        }
+        let history =
+            &mut self.history[side as usize * Square::COUNT * Square::COUNT + move_index(step)];
+        *history = history.saturating_add(depth * depth);
+        if let Some(last) = board.get_last_move() {
+            self.countermoves[move_index(&last.mov)] = Some(*step);
+        }
+    }
+
+    /// 计入一个节点，返回是否需要中断搜索
+    fn visit(&mut self) -> bool {
+        self.node_count += 1;
+        self.stop_signal.is_stopped()
+            || self
+                .max_node_count
+                .is_some_and(|max_node_count| self.node_count > max_node_count)
+            || (self.node_count.is_multiple_of(TIME_CHECK_INTERVAL)
+                && self.time_manager.out_of_time())
+    }
+}
+
+impl Decision {
+    /// 获取决定中的走子，不走子时为 `None`
+    pub fn get_move(&self) -> Option<Move> {
+        match self {
+            Decision::Move(mov) | Decision::OfferDraw(mov) => Some(*mov),
+            _ => None,
+        }
+    }
+}
+
+impl From<Option<Move>> for Decision {
+    /// 有走法时走子，无子可走时认输
+    fn from(mov: Option<Move>) -> Decision {
+        match mov {
+            Some(mov) => Decision::Move(mov),
+            None => Decision::Resign,
+        }
+    }
+}
+
+impl<E: Evaluator + Send + Sync + 'static> MaxMinDecider<E> {
+    /// 清空置换表，通常在两盘棋之间调用
+    pub fn clear_transposition_table(&self) {
+        self.transposition_table.clear();
+    }
+
+    /// 获取选择性搜索配置
+    pub fn get_config(&self) -> &SearchConfig {
+        &self.config
+    }
+
+    /// 获取评估器
+    pub fn get_evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// 获取搜索限制
+    pub fn get_limits(&self) -> &SearchLimits {
+        &self.limits
+    }
+
+    /// 获取停止信号，可以复制后交给其他线程以中断搜索
+    pub fn get_stop_signal(&self) -> &StopSignal {
+        &self.stop_signal
+    }
+
+    /// 获取搜索线程数
+    pub fn get_thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// 查询是否正在后台思考
+    pub fn is_pondering(&self) -> bool {
+        self.ponder_job.is_some()
+    }
+
+    /// 在搜索限制内迭代加深搜索，返回最好的若干个候选走法，按分数从高到低排列
+    ///
+    /// 每轮迭代中依次搜索各个候选，每次排除之前已选出的走法。每完成一轮迭代，
+    /// 主线程对每个候选调用一次进度回调。搜索因限制或停止信号中断时，
+    /// 只有一个候选且本轮迭代已经找到更好的走法时采用之，否则采用上一轮迭代的结果。
+    /// 编号为奇数的辅助线程从第二层开始，以便与其他线程错开深度。
+    fn iterative_deepening(
+        &self,
+        board: &Board,
+        side: Side,
+        line_count: usize,
+        thread_index: usize,
+        state: &mut SearchState,
+    ) -> Vec<SearchResult> {
+        let line_count = line_count.min(board.query_possible_moves_of_side(side).count());
+        let mut ret: Vec<SearchResult> = Vec::new();
+        let first_depth = 1 + (thread_index % 2) as u32;
+        for depth in first_depth..=self.limits.depth.unwrap_or(MAX_DEPTH).min(MAX_DEPTH) {
+            if depth > first_depth && !state.time_manager.should_start_iteration() {
+                break;
+            }
+            let mut lines: Vec<(Score, SearchResult)> = Vec::new();
+            let mut aborted = false;
+            for index in 0..line_count {
+                let excluded: Vec<Move> = lines
+                    .iter()
+                    .filter_map(|(_, line)| line.best_move)
+                    .collect();
+                let previous_pv = ret
+                    .get(index)
+                    .map_or(&[][..], |line| &line.principal_variation[..]);
+                let mut playground = board.clone();
+                let mut pv = Vec::new();
+                let score = self.search_root(
+                    &mut playground,
+                    side,
+                    depth,
+                    previous_pv,
+                    &excluded,
+                    state,
+                    &mut pv,
+                );
+                if score.is_none() {
+                    aborted = true;
+                }
+                if !pv.is_empty() && (score.is_some() || line_count == 1 || ret.is_empty()) {
+                    // 被中断的一轮只采用其中找到的走法，深度仍为已完成的上一轮
+                    let completed_depth = if score.is_some() { depth } else { depth - 1 };
+                    let score = score.unwrap_or(state.root_best);
+                    lines.push((
+                        score,
+                        SearchResult {
+                            best_move: pv.first().copied(),
+                            score: score.into(),
+                            depth: completed_depth,
+                            ponder_move: pv.get(1).copied(),
+                            principal_variation: pv,
+                            ..SearchResult::default()
+                        },
+                    ));
+                }
+                if aborted {
+                    break;
+                }
+            }
+            lines.sort_by_key(|(score, _)| Reverse(*score));
+            // 中断时只在有更好的结果可用时才替换上一轮迭代的结果
+            if !aborted || ret.is_empty() || (line_count == 1 && !lines.is_empty()) {
+                ret = lines.iter().map(|(_, line)| line.clone()).collect();
+            }
+            for line in ret.iter_mut() {
+                line.node_count = state.node_count;
+                line.cutoff_count = state.cutoff_count;
+                line.first_move_cutoff_count = state.first_move_cutoff_count;
+                line.elapsed = state.time_manager.elapsed();
+            }
+            if aborted {
+                break;
+            }
+            if thread_index == 0 {
+                for callback in [&self.progress_callback, &self.search_info_callback]
+                    .into_iter()
+                    .flatten()
+                {
+                    for line in &ret {
+                        callback.call(line);
+                    }
+                }
+            }
+            // 所有候选都已经算出胜负，无需继续加深
+            if lines.iter().all(|(score, _)| score.is_mate()) {
+                break;
+            }
         }
+        ret
     }
 
-    /// 最大搜索
-    fn max_search(
+    /// 负极大值主要变例搜索
+    ///
+    /// 返回以 `side` 为走子方视角的分数，并将主要变例写入 `pv`。
+    /// 超出节点数限制时返回 `None`。被将军时延伸一层，
+    /// 否则在零窗口节点中视配置使用剃刀剪枝和空着剪枝。
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
         &self,
         board: &mut Board,
         side: Side,
-        depth: u32,
-        current_node_count: &mut u32,
-        mut alpha: f32,
-        beta: f32,
-    ) -> Option<f32> {
-        *current_node_count += 1;
-        if *current_node_count > self.max_node_count {
+        mut depth: u32,
+        ply: u32,
+        mut alpha: Score,
+        beta: Score,
+        state: &mut SearchState,
+        pv: &mut Vec<Move>,
+    ) -> Option<Score> {
+        pv.clear();
+        let in_check = (depth > 0 || self.config.check_extension) && board.general_threatened(side);
+        if in_check && self.config.check_extension {
+            depth += 1;
+        }
+        if depth == 0 {
+            return self.quiescence(board, side, ply, alpha, beta, state);
+        }
+        if state.visit() {
             return None;
         }
-        if depth == 0 || board.game_finished() {
-            return Some(self.evaluator.evaluate(board, side));
+        if board.game_finished() {
+            return Some(terminal_score(board, side, ply));
         }
-        let mut ret = 0.0f32;
-        for step in board.query_possible_moves_of_side(side) {
-            board.apply_move_unchecked(&step);
-            let score = self.min_search(board, side, depth - 1, current_node_count, alpha, beta)?;
-            board.undo_move().unwrap();
-            if score > ret {
-                ret = score;
+        // 查询置换表，深度足够时直接截断，否则至少取得最佳走法
+        let key = TranspositionTable::key(board.get_hash(), side);
+        let mut hash_move = None;
+        if let Some(entry) = self.transposition_table.probe(key) {
+            hash_move = entry.best_move;
+            let score = entry.score.from_table(ply);
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => {
+                        pv.extend(entry.best_move);
+                        return Some(score);
+                    }
+                    Bound::Lower if score >= beta => return Some(score),
+                    Bound::Upper if score <= alpha => return Some(score),
+                    _ => {}
+                }
             }
-            if ret > alpha {
-                alpha = ret;
+        }
+        let static_eval = if in_check {
+            None
+        } else {
+            Some(self.evaluator.evaluate(board, side))
+        };
+        if let Some(static_eval) = static_eval.filter(|_| is_null_window(alpha, beta)) {
+            // 剃刀剪枝：估值远低于 alpha 时以静态搜索验证
+            if self.config.razoring
+                && depth <= FRONTIER_MAX_DEPTH
+                && static_eval + self.config.razoring_margin * depth as i32 <= alpha
+            {
+                let score = self.quiescence(board, side, ply, alpha, beta, state)?;
+                if score <= alpha {
+                    return Some(score);
+                }
             }
-            if alpha > beta {
-                break;
+            // 空着剪枝：让对方连走两步仍不低于 beta 时直接截断
+            if self.config.null_move
+                && depth >= NULL_MOVE_MIN_DEPTH
+                && static_eval >= beta
+                && state
+                    .null_move_ply
+                    .is_none_or(|null_ply| null_ply + 1 != ply)
+                && has_attacking_pieces(board, side)
+            {
+                let reduction = if depth > 6 { 3 } else { 2 };
+                let previous = state.null_move_ply.replace(ply);
+                let mut child_pv = Vec::new();
+                let score = self
+                    .negamax(
+                        board,
+                        side.other(),
+                        depth.saturating_sub(1 + reduction),
+                        ply + 1,
+                        -beta,
+                        -null_window_alpha(beta),
+                        state,
+                        &mut child_pv,
+                    )
+                    .map(|score| -score);
+                state.null_move_ply = previous;
+                let score = score?;
+                if score >= beta {
+                    // 空着得到的杀棋分数不可靠
+                    return Some(if score.is_mate() { beta } else { score });
+                }
+            }
+        }
+        let steps = self.order_moves(board, side, ply, hash_move, state);
+        let original_alpha = alpha;
+        let score = self.search_moves(
+            board,
+            side,
+            depth,
+            ply,
+            &mut alpha,
+            beta,
+            steps,
+            static_eval,
+            state,
+            pv,
+        )?;
+        self.store(key, depth, ply, original_alpha, beta, score, pv);
+        Some(score)
+    }
+
+    /// 以节点数限制构造
+    pub fn new(evaluator: E, max_node_count: u32) -> MaxMinDecider<E> {
+        MaxMinDecider::with_limits(evaluator, SearchLimits::nodes(max_node_count as u64))
+    }
+
+    /// 生成走法并排序
+    ///
+    /// 依次为：置换表中的最佳走法，静态交换评估不为负的吃子（按最有价值受害者、
+    /// 最低价值攻击者排列），杀手走法，应着，按历史分数排列的其余非吃子走法，
+    /// 最后是静态交换评估为负的吃子。
+    fn order_moves(
+        &self,
+        board: &Board,
+        side: Side,
+        ply: u32,
+        hash_move: Option<Move>,
+        state: &SearchState,
+    ) -> Vec<Move> {
+        let killers = state.get_killers(ply);
+        let countermove = state.get_countermove(board);
+        let mut steps: Vec<Move> = board.query_possible_moves_of_side(side).collect();
+        steps.sort_by_cached_key(|step| {
+            if Some(*step) == hash_move {
+                return Reverse((5, 0));
+            }
+            if let Some(victim) = board.get_piece_at(step.pos_to) {
+                let attacker = board
+                    .get_piece_at(step.pos_from)
+                    .map_or(0, |piece| self.evaluator.piece_value(piece));
+                let mvv_lva = self.evaluator.piece_value(victim) as i64 * 4096 - attacker as i64;
+                let winning = victim.kind == PieceKind::帥
+                    || board.see(step, |piece| self.evaluator.piece_value(piece)) >= 0;
+                return Reverse((if winning { 4 } else { 0 }, mvv_lva));
+            }
+            if let Some(slot) = killers.iter().position(|killer| *killer == Some(*step)) {
+                return Reverse((3, (KILLER_COUNT - slot) as i64));
+            }
+            if countermove == Some(*step) {
+                return Reverse((2, 0));
+            }
+            Reverse((1, state.get_history(side, step) as i64))
+        });
+        steps
+    }
+
+    /// 多线程搜索，返回主线程的结果
+    ///
+    /// 辅助线程与主线程搜索同一局面，只通过共享的置换表互相帮助，不受节点数和时间限制，
+    /// 在主线程结束后停止。结果中的节点数只计主线程。只有一个线程时不创建新线程，
+    /// 结果与单线程搜索相同。
+    fn parallel_search(&self, board: &Board, side: Side, line_count: usize) -> Vec<SearchResult> {
+        self.transposition_table.new_search();
+        let mut state = SearchState::new(&self.limits, &self.stop_signal, self.rng.fork());
+        if self.thread_count <= 1 {
+            return self.iterative_deepening(board, side, line_count, 0, &mut state);
+        }
+        let helper_stop_signal = StopSignal::new();
+        let helper_limits = SearchLimits {
+            depth: self.limits.depth,
+            ..SearchLimits::infinite()
+        };
+        thread::scope(|scope| {
+            for thread_index in 1..self.thread_count {
+                let helper_stop_signal = helper_stop_signal.clone();
+                let rng = self.rng.fork();
+                scope.spawn(move || {
+                    let mut state = SearchState::new(&helper_limits, &helper_stop_signal, rng);
+                    self.iterative_deepening(board, side, line_count, thread_index, &mut state);
+                });
             }
+            let ret = self.iterative_deepening(board, side, line_count, 0, &mut state);
+            helper_stop_signal.stop();
+            ret
+        })
+    }
+
+    /// 构造后台思考用的搜索器，与自身共享评估器和置换表，只受深度和节点数限制
+    fn ponder_searcher(&self) -> MaxMinDecider<E> {
+        MaxMinDecider {
+            config: self.config,
+            evaluator: self.evaluator.clone(),
+            limits: SearchLimits {
+                depth: self.limits.depth,
+                nodes: self.limits.nodes,
+                ..SearchLimits::infinite()
+            },
+            ponder_job: None,
+            pondering: false,
+            progress_callback: None,
+            rng: DeciderRng(Mutex::new(self.rng.fork())),
+            search_info_callback: None,
+            stop_signal: StopSignal::new(),
+            thread_count: self.thread_count,
+            transposition_table: self.transposition_table.clone(),
         }
-        Some(ret)
     }
 
-    /// 最小搜索
-    fn min_search(
+    /// 在搜索限制内迭代加深搜索，返回主要变例
+    pub fn principal_variation(&self, board: &Board, side: Side) -> Vec<Move> {
+        self.parallel_search(board, side, 1)
+            .pop()
+            .unwrap_or_default()
+            .principal_variation
+    }
+
+    /// 静态搜索，只考虑吃子，直到局面平静为止，以避免水平线效应
+    ///
+    /// 走子方没有被将军时可以选择不吃子而接受当前估值；被将军时不能停着，搜索所有应将的走法。
+    /// 吃子按最有价值受害者、最低价值攻击者的顺序搜索，没有被将军时，
+    /// 即使吃到的子全部计入也无法超过 alpha 的吃子（增量剪枝），
+    /// 以及静态交换评估为负的吃子不予考虑。
+    fn quiescence(
         &self,
         board: &mut Board,
         side: Side,
-        depth: u32,
-        current_node_count: &mut u32,
-        alpha: f32,
-        mut beta: f32,
-    ) -> Option<f32> {
-        *current_node_count += 1;
-        if *current_node_count > self.max_node_count {
+        ply: u32,
+        mut alpha: Score,
+        beta: Score,
+        state: &mut SearchState,
+    ) -> Option<Score> {
+        if state.visit() {
             return None;
         }
-        if depth == 0 || board.game_finished() {
-            return Some(self.evaluator.evaluate(board, side));
+        if board.game_finished() {
+            return Some(terminal_score(board, side, ply));
         }
-        let mut ret = 1.0f32;
-        for step in board.query_possible_moves_of_side(side.other()) {
-            board.apply_move_unchecked(&step);
-            let score = self.max_search(board, side, depth - 1, current_node_count, alpha, beta)?;
-            board.undo_move().unwrap();
-            if score < ret {
-                ret = score;
+        let in_check = board.general_threatened(side);
+        let mut best = Score::mated_in(ply);
+        let mut stand_pat = None;
+        if !in_check {
+            let eval = self.evaluator.evaluate(board, side);
+            if eval >= beta {
+                return Some(eval);
             }
-            if ret < beta {
-                beta = ret;
+            if eval > alpha {
+                alpha = eval;
             }
-            if alpha > beta {
-                break;
+            best = eval;
+            stand_pat = Some(eval);
+        }
+        let mut steps: Vec<(Move, Option<Piece>, Piece)> = board
+            .query_possible_moves_of_side(side)
+            .filter_map(|step| {
+                let victim = board.get_piece_at(step.pos_to);
+                if victim.is_none() && !in_check {
+                    return None;
+                }
+                let attacker = board.get_piece_at(step.pos_from)?;
+                Some((step, victim, attacker))
+            })
+            .collect();
+        steps.sort_by_key(|(_, victim, attacker)| {
+            (
+                -victim.map_or(0, |victim| self.evaluator.piece_value(victim)),
+                self.evaluator.piece_value(*attacker),
+            )
+        });
+        for (step, victim, _) in steps {
+            if let (Some(stand_pat), Some(victim)) = (stand_pat, victim) {
+                if victim.kind != PieceKind::帥 {
+                    if stand_pat + self.evaluator.piece_value(victim) + DELTA_MARGIN < alpha {
+                        continue;
+                    }
+                    if board.see(&step, |piece| self.evaluator.piece_value(piece)) < 0 {
+                        continue;
+                    }
+                }
+            }
+            board.apply_move_unchecked(&step);
+            let score = self
+                .quiescence(board, side.other(), ply + 1, -beta, -alpha, state)
+                .map(|score| -score);
+            board.undo_move().unwrap();
+            let score = score?;
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                }
+                if score >= beta {
+                    break;
+                }
             }
         }
-        Some(ret)
+        Some(best)
     }
-}
 
-impl<E: Evaluator> Decider for MaxMinDecider<E> {
-    /// 作出走子决定
-    fn make_decision(&self, board: &Board, side: Side) -> Option<Move> {
-        let mut ret = None;
-        for depth in 1..1000 {
-            let mut playground = board.clone();
-            let mut decision = None;
-            let mut current_node_count = 0;
-            let mut max_score = -1.0f32;
-            let mut random_value = 0.0f32;
-            let mut alpha = 0.0f32;
-            for step in board.query_possible_moves_of_side(side) {
-                playground.apply_move_unchecked(&step);
-                if playground.game_finished() {
-                    return Some(step);
+    /// 依次搜索给定走法，供根节点和内部节点共用
+    ///
+    /// `static_eval` 为 `None` 时不作前沿节点剪枝和后期走法衰减，用于根节点和被将军的节点。
+    #[allow(clippy::too_many_arguments)]
+    fn search_moves(
+        &self,
+        board: &mut Board,
+        side: Side,
+        depth: u32,
+        ply: u32,
+        alpha: &mut Score,
+        beta: Score,
+        steps: Vec<Move>,
+        static_eval: Option<Score>,
+        state: &mut SearchState,
+        pv: &mut Vec<Move>,
+    ) -> Option<Score> {
+        // 无子可走，判负
+        let mut best = Score::mated_in(ply);
+        let mut child_pv = Vec::new();
+        let killers = state.get_killers(ply);
+        for (index, step) in steps.into_iter().enumerate() {
+            let quiet = board.get_piece_at(step.pos_to).is_none();
+            board.apply_move_unchecked(&step);
+            let mut reduction = 0;
+            if let Some(static_eval) = static_eval {
+                if index > 0 && quiet && !board.general_threatened(side.other()) {
+                    // 前沿节点剪枝：估值加上余量仍不超过 alpha 的平静走法不再搜索
+                    let futility_value = static_eval + self.config.futility_margin * depth as i32;
+                    if self.config.futility_pruning
+                        && depth <= FRONTIER_MAX_DEPTH
+                        && is_null_window(*alpha, beta)
+                        && futility_value <= *alpha
+                    {
+                        board.undo_move().unwrap();
+                        best = best.max(futility_value);
+                        continue;
+                    }
+                    // 后期走法衰减：排在后面的平静走法先以较浅的深度搜索
+                    if self.config.late_move_reduction
+                        && depth >= LATE_MOVE_MIN_DEPTH
+                        && index >= LATE_MOVE_MIN_INDEX
+                        && !killers.contains(&Some(step))
+                    {
+                        reduction = if depth >= 6 && index >= 6 { 2 } else { 1 };
+                    }
                 }
-                let score = self.min_search(
-                    &mut playground,
-                    side,
+            }
+            let score = if index == 0 {
+                self.negamax(
+                    board,
+                    side.other(),
                     depth - 1,
-                    &mut current_node_count,
-                    alpha,
-                    1.0f32,
-                );
-                playground.undo_move().unwrap();
+                    ply + 1,
+                    -beta,
+                    -*alpha,
+                    state,
+                    &mut child_pv,
+                )
+                .map(|score| -score)
+            } else {
+                // 先以零窗口验证该走法不优于已有最佳走法，否则以完整窗口重新搜索
+                let null_beta = -*alpha;
+                let null_alpha = null_window_alpha(null_beta);
+                let mut score = self
+                    .negamax(
+                        board,
+                        side.other(),
+                        depth - 1 - reduction,
+                        ply + 1,
+                        null_alpha,
+                        null_beta,
+                        state,
+                        &mut child_pv,
+                    )
+                    .map(|score| -score);
+                // 衰减后超过 alpha 时恢复深度重新验证
+                if reduction > 0 && score.is_some_and(|score| score > *alpha) {
+                    score = self
+                        .negamax(
+                            board,
+                            side.other(),
+                            depth - 1,
+                            ply + 1,
+                            null_alpha,
+                            null_beta,
+                            state,
+                            &mut child_pv,
+                        )
+                        .map(|score| -score);
+                }
                 match score {
-                    Some(score) => {
-                        if score > max_score {
-                            max_score = score;
-                            decision = Some(step);
-                            random_value = random();
-                            if max_score > alpha {
-                                alpha = max_score;
-                            }
-                            // alpha 不可能大于 beta
-                            // 写出不规范 evaluator 的就应该命丧当场（狗头）
-                        } else if score == max_score {
-                            let new_random_value = random();
-                            if new_random_value > random_value {
-                                decision = Some(step);
-                                random_value = new_random_value;
-                            }
-                        }
-                    }
-                    None => {
-                        return ret;
-                    }
+                    Some(score) if score > *alpha && score < beta => self
+                        .negamax(
+                            board,
+                            side.other(),
+                            depth - 1,
+                            ply + 1,
+                            -beta,
+                            -*alpha,
+                            state,
+                            &mut child_pv,
+                        )
+                        .map(|score| -score),
+                    score => score,
+                }
+            };
+            board.undo_move().unwrap();
+            let score = score?;
+            if score > best {
+                best = score;
+                if score > *alpha {
+                    *alpha = score;
+                    pv.clear();
+                    pv.push(step);
+                    pv.extend_from_slice(&child_pv);
+                }
+                if score >= beta {
+                    state.record_cutoff(board, side, &step, depth, ply, index);
+                    break;
                 }
             }
-            ret = decision;
+        }
+        Some(best)
+    }
+
+    /// 根节点搜索，不考虑 `excluded` 中的走法
+    ///
+    /// 上一次迭代的最佳走法优先，其余走法随机排列以在同分时随机选择。
+    #[allow(clippy::too_many_arguments)]
+    fn search_root(
+        &self,
+        board: &mut Board,
+        side: Side,
+        depth: u32,
+        previous_pv: &[Move],
+        excluded: &[Move],
+        state: &mut SearchState,
+        pv: &mut Vec<Move>,
+    ) -> Option<Score> {
+        if state.visit() {
+            return None;
+        }
+        let mut steps: Vec<Move> = board
+            .query_possible_moves_of_side(side)
+            .filter(|step| !excluded.contains(step))
+            .collect();
+        steps.shuffle(&mut state.rng);
+        if let Some(best) = previous_pv.first() {
+            if let Some(index) = steps.iter().position(|step| step == best) {
+                let step = steps.remove(index);
+                steps.insert(0, step);
+            }
+        }
+        let mut alpha = -Score::INFINITY;
+        let score = self.search_moves(
+            board,
+            side,
+            depth,
+            0,
+            &mut alpha,
+            Score::INFINITY,
+            steps,
+            None,
+            state,
+            pv,
+        );
+        // 被中断时 alpha 即为已搜完走法中的最佳分数
+        state.root_best = alpha;
+        let score = score?;
+        // 排除了部分走法时得到的不是该局面的真实分数
+        if excluded.is_empty() {
+            let key = TranspositionTable::key(board.get_hash(), side);
+            self.store(key, depth, 0, -Score::INFINITY, Score::INFINITY, score, pv);
+        }
+        Some(score)
+    }
+
+    /// 设置选择性搜索配置
+    pub fn set_config(&mut self, config: SearchConfig) {
+        self.config = config;
+    }
+
+    /// 设置是否在对方思考时后台思考
+    ///
+    /// 开启后每次作出决定时，假定对方按主要变例应着，立即在后台线程中搜索之后的局面，
+    /// 后台思考只受深度和节点数限制。对方确实如此应着时（猜中），下一次决定继续等待后台思考
+    /// 直到达到深度或节点数限制，并采用其结果；有时间限制时，后台思考已用的时间计入本次决定，
+    /// 超出时立即采用已有的结果。没有猜中时停止后台思考，重新搜索，但置换表中的结果仍可利用。
+    pub fn set_pondering(&mut self, pondering: bool) {
+        self.pondering = pondering;
+        if !pondering {
+            self.stop_pondering();
+        }
+    }
+
+    /// 设置搜索线程数，至少为 1，所有线程共享置换表
+    pub fn set_thread_count(&mut self, thread_count: usize) {
+        self.thread_count = thread_count.max(1);
+    }
+
+    /// 设置置换表大小，单位为 MiB，原有内容会被清空
+    pub fn set_transposition_table_size(&mut self, megabytes: usize) {
+        self.stop_pondering();
+        self.transposition_table = Arc::new(TranspositionTable::new(megabytes));
+    }
+
+    /// 按决定的结果开始后台思考
+    fn start_pondering(&mut self, board: &Board, side: Side, result: &SearchResult) {
+        self.stop_pondering();
+        let (Some(best_move), Some(ponder_move)) = (result.best_move, result.ponder_move) else {
+            return;
+        };
+        let mut expected = board.clone();
+        expected.apply_move_unchecked(&best_move);
+        if expected.game_finished() {
+            return;
+        }
+        expected.apply_move_unchecked(&ponder_move);
+        if expected.game_finished() {
+            return;
+        }
+        let searcher = self.ponder_searcher();
+        let stop_signal = searcher.stop_signal.clone();
+        let expected_key = TranspositionTable::key(expected.get_hash(), side);
+        let handle = thread::spawn(move || searcher.parallel_search(&expected, side, 1).pop());
+        self.ponder_job = Some(PonderJob {
+            side,
+            expected_key,
+            hit: false,
+            start: Instant::now(),
+            stop_signal,
+            handle,
+        });
+    }
+
+    /// 停止后台思考并丢弃其结果
+    fn stop_pondering(&mut self) {
+        if let Some(job) = self.ponder_job.take() {
+            job.stop_signal.stop();
+            let _ = job.handle.join();
+        }
+    }
+
+    /// 将搜索结果写入置换表
+    #[allow(clippy::too_many_arguments)]
+    fn store(
+        &self,
+        key: u64,
+        depth: u32,
+        ply: u32,
+        alpha: Score,
+        beta: Score,
+        score: Score,
+        pv: &[Move],
+    ) {
+        let bound = if score <= alpha {
+            Bound::Upper
+        } else if score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.transposition_table.store(
+            key,
+            TableEntry {
+                depth,
+                bound,
+                score: score.to_table(ply),
+                best_move: pv.first().copied(),
+            },
+        );
+    }
+
+    /// 猜中时继续等待后台思考，返回其结果；没有猜中或不能采用时返回 `None`
+    ///
+    /// 后台思考自身受深度和节点数限制；有时间限制时从开始后台思考起计时。
+    fn take_ponder_result(&mut self, board: &Board, side: Side) -> Option<SearchResult> {
+        let job = self.ponder_job.take()?;
+        let time_manager = TimeManager::new(&self.limits);
+        let usable = job.hit
+            && job.side == side
+            && job.expected_key == TranspositionTable::key(board.get_hash(), side);
+        if usable {
+            let soft_limit = time_manager.get_soft_limit();
+            while !job.handle.is_finished()
+                && soft_limit.is_none_or(|limit| job.start.elapsed() < limit)
+                && !self.stop_signal.is_stopped()
+            {
+                thread::sleep(PONDER_POLL_INTERVAL);
+            }
+        }
+        job.stop_signal.stop();
+        let result = job.handle.join().ok().flatten().filter(|_| usable)?;
+        result.best_move.map(|_| SearchResult {
+            elapsed: time_manager.elapsed(),
+            ..result
+        })
+    }
+
+    /// 以搜索限制构造
+    pub fn with_limits(evaluator: E, limits: SearchLimits) -> MaxMinDecider<E> {
+        Self {
+            config: SearchConfig::default(),
+            evaluator: Arc::new(evaluator),
+            limits,
+            ponder_job: None,
+            pondering: false,
+            progress_callback: None,
+            rng: DeciderRng::new(),
+            search_info_callback: None,
+            stop_signal: StopSignal::new(),
+            thread_count: 1,
+            transposition_table: Arc::new(TranspositionTable::new(
+                DEFAULT_TRANSPOSITION_TABLE_SIZE,
+            )),
+        }
+    }
+}
+
+impl<E: Evaluator + Clone> Clone for MaxMinDecider<E> {
+    /// 复制，包括置换表的内容，但不包括正在进行的后台思考
+    fn clone(&self) -> MaxMinDecider<E> {
+        MaxMinDecider {
+            config: self.config,
+            evaluator: Arc::new(E::clone(&self.evaluator)),
+            limits: self.limits,
+            ponder_job: None,
+            pondering: self.pondering,
+            progress_callback: self.progress_callback.clone(),
+            rng: self.rng.clone(),
+            search_info_callback: self.search_info_callback.clone(),
+            stop_signal: self.stop_signal.clone(),
+            thread_count: self.thread_count,
+            transposition_table: Arc::new(TranspositionTable::clone(&self.transposition_table)),
+        }
+    }
+}
+
+impl<E: Evaluator> Drop for MaxMinDecider<E> {
+    /// 停止后台思考
+    fn drop(&mut self) {
+        if let Some(job) = self.ponder_job.take() {
+            job.stop_signal.stop();
+            let _ = job.handle.join();
+        }
+    }
+}
+
+/// 查询某方是否还有车、马、炮，没有时容易出现等着，不使用空着剪枝
+fn has_attacking_pieces(board: &Board, side: Side) -> bool {
+    Square::all().any(|pos| {
+        board.get_piece_at(pos).is_some_and(|piece| {
+            piece.side == side
+                && matches!(piece.kind, PieceKind::車 | PieceKind::馬 | PieceKind::炮)
+        })
+    })
+}
+
+/// 查询是否为零窗口，即非主要变例节点
+fn is_null_window(alpha: Score, beta: Score) -> bool {
+    alpha >= null_window_alpha(beta)
+}
+
+/// 由零窗口的 beta 得到 alpha
+fn null_window_alpha(beta: Score) -> Score {
+    beta - 1
+}
+
+/// 由起点和终点得到走法在历史表和应着表中的下标
+fn move_index(step: &Move) -> usize {
+    step.pos_from.index() * Square::COUNT + step.pos_to.index()
+}
+
+/// 按走子后的静态估值选择走法，用于一步都没有搜完时，无子可走时为 `None`
+pub(crate) fn static_best_move<E: Evaluator>(
+    evaluator: &E,
+    board: &Board,
+    side: Side,
+) -> Option<Move> {
+    let mut playground = board.clone();
+    board.query_possible_moves_of_side(side).max_by_key(|mov| {
+        playground.apply_move_unchecked(mov);
+        let score = if playground.game_finished() {
+            terminal_score(&playground, side, 1)
+        } else {
+            evaluator.evaluate(&playground, side)
+        };
+        playground.undo_move().unwrap();
+        score
+    })
+}
+
+/// 游戏结束时以走子方视角的分数
+fn terminal_score(board: &Board, side: Side, ply: u32) -> Score {
+    match board.get_winner() {
+        Some(winner) if winner == side => Score::mate_in(ply),
+        Some(_) => Score::mated_in(ply),
+        None => Score::DRAW,
+    }
+}
+
+impl<E: Evaluator + Send + Sync + 'static> Decider for MaxMinDecider<E> {
+    /// 一局结束，停止后台思考
+    fn game_over(&mut self, _outcome: &GameOutcome) {
+        self.stop_pondering();
+    }
+
+    /// 作出走子决定，一步都没有搜完时按走子后的静态估值选择，无子可走时认输
+    fn make_decision(&mut self, board: &Board, side: Side) -> Decision {
+        self.search(board, side)
+            .best_move
+            .or_else(|| static_best_move(&*self.evaluator, board, side))
+            .into()
+    }
+
+    /// 新的一局开始，停止后台思考并清空置换表
+    fn new_game(&mut self, _board: &Board, _side: Side) {
+        self.stop_pondering();
+        self.clear_transposition_table();
+    }
+
+    /// 对方走了一步，与后台思考时的预计相符则记为猜中，否则停止后台思考
+    fn opponent_moved(&mut self, board: &Board, _mov: &Move) {
+        if let Some(job) = &mut self.ponder_job {
+            if job.expected_key == TranspositionTable::key(board.get_hash(), job.side) {
+                job.hit = true;
+            } else {
+                self.stop_pondering();
+            }
+        }
+    }
+
+    /// 作出走子决定，并返回分数、深度、节点数、用时和主要变例
+    ///
+    /// 限制过紧或被停止以至于一步都没有搜完时，结果中没有走法且深度为 0。
+    /// 开启后台思考时，返回前开始思考对方按主要变例应着后的局面。
+    fn search(&mut self, board: &Board, side: Side) -> SearchResult {
+        let ret = match self.take_ponder_result(board, side) {
+            Some(ret) => ret,
+            None => self
+                .parallel_search(board, side, 1)
+                .pop()
+                .unwrap_or_default(),
+        };
+        if self.pondering {
+            self.start_pondering(board, side, &ret);
         }
         ret
     }
+
+    /// 多变例分析，返回最好的若干个候选走法及其分数和主要变例
+    fn search_multi_pv(
+        &mut self,
+        board: &Board,
+        side: Side,
+        line_count: usize,
+    ) -> Vec<SearchResult> {
+        self.parallel_search(board, side, line_count)
+    }
+
+    /// 设置棋钟，与其余搜索限制一起生效
+    fn set_clock(&mut self, clock: Option<Clock>) {
+        self.limits.clock = clock;
+    }
+
+    /// 设置搜索限制
+    fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    /// 设置是否在对方思考时后台思考
+    fn set_pondering(&mut self, pondering: bool) {
+        MaxMinDecider::set_pondering(self, pondering);
+    }
+
+    /// 设置进度回调，每完成一轮迭代加深调用一次，后台思考时不调用
+    fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress_callback = Some(callback);
+    }
+
+    /// 设置向对局观察者报告搜索信息的回调，与进度回调在同样的时机调用
+    fn set_search_info_callback(&mut self, callback: Option<ProgressCallback>) {
+        self.search_info_callback = callback;
+    }
+
+    /// 设置随机数种子，用于在同分时随机选择，未设置时使用固定的种子
+    fn set_seed(&mut self, seed: u64) {
+        self.rng.set_seed(seed);
+    }
+
+    /// 设置停止信号
+    fn set_stop_signal(&mut self, signal: StopSignal) {
+        self.stop_signal = signal;
+    }
 }
 
 impl RandomDecider {
     /// 构造
     pub fn new() -> RandomDecider {
-        RandomDecider {}
+        RandomDecider::default()
+    }
+}
+
+impl DeciderRng {
+    /// 派生一个独立的随机数发生器
+    pub(crate) fn fork(&self) -> StdRng {
+        StdRng::seed_from_u64(self.0.lock().unwrap().gen())
+    }
+
+    /// 以给定的种子构造
+    pub(crate) fn from_seed(seed: u64) -> DeciderRng {
+        DeciderRng(Mutex::new(StdRng::seed_from_u64(seed)))
+    }
+
+    /// 以默认的固定种子构造
+    pub(crate) fn new() -> DeciderRng {
+        DeciderRng::from_seed(DEFAULT_SEED)
+    }
+
+    /// 设置种子
+    pub(crate) fn set_seed(&self, seed: u64) {
+        *self.0.lock().unwrap() = StdRng::seed_from_u64(seed);
+    }
+}
+
+impl Clone for DeciderRng {
+    /// 复制，包括当前状态
+    fn clone(&self) -> DeciderRng {
+        DeciderRng(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+impl fmt::Debug for DeciderRng {
+    /// 不输出内部状态
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DeciderRng")
+    }
+}
+
+impl Default for DeciderRng {
+    /// 以默认的固定种子构造
+    fn default() -> DeciderRng {
+        DeciderRng::new()
     }
 }
 
 impl Decider for RandomDecider {
-    /// 作出走子决定
-    fn make_decision(&self, board: &Board, side: Side) -> Option<Move> {
-        let mut steps: Vec<Move> = board.query_possible_moves_of_side(side).collect();
-        if steps.is_empty() {
-            None
-        } else {
-            let mut rng = thread_rng();
-            steps.shuffle(&mut rng);
-            Some(steps[0].clone())
-        }
+    /// 作出走子决定，无子可走时认输
+    fn make_decision(&mut self, board: &Board, side: Side) -> Decision {
+        let steps: Vec<Move> = board.query_possible_moves_of_side(side).collect();
+        steps.choose(&mut self.rng.fork()).copied().into()
+    }
+
+    /// 设置随机数种子
+    fn set_seed(&mut self, seed: u64) {
+        self.rng.set_seed(seed);
     }
 }
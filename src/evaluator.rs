@@ -3,12 +3,35 @@
  * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
  */
 
-use crate::board::{Board, Piece, PieceKind, Side};
+use crate::{
+    board::{Board, Piece, PieceKind, Side},
+    score::Score,
+    square::Square,
+};
 
 /// 局面评估器接口
 pub trait Evaluator {
-    /// 估价
-    fn evaluate(&self, board: &Board, side: Side) -> f32;
+    /// 以 `side` 的视角估价，正数表示对 `side` 有利
+    fn evaluate(&self, board: &Board, side: Side) -> Score;
+
+    /// 获取棋子的子力价值，单位与估值相同
+    ///
+    /// 用于静态交换评估、吃子排序和静态搜索中的增量剪枝，
+    /// 因此吃掉某棋子后估值的提高不应明显超过其子力价值。
+    fn piece_value(&self, piece: Piece) -> i32 {
+        match piece.kind {
+            PieceKind::帥 => 2000,
+            PieceKind::車 => 100,
+            PieceKind::馬 => 50,
+            PieceKind::炮 => 50,
+            PieceKind::相 => 30,
+            PieceKind::仕 => 30,
+            PieceKind::中兵 => 25,
+            PieceKind::濟兵 => 25,
+            PieceKind::庶兵 => 20,
+            PieceKind::底兵 => 15,
+        }
+    }
 }
 
 /// 简单的局面评估器实现
@@ -60,34 +83,35 @@ impl SimpleEvaluator {
     }
 }
 
+impl Default for SimpleEvaluator {
+    /// 构造
+    fn default() -> SimpleEvaluator {
+        SimpleEvaluator::new()
+    }
+}
+
 impl Evaluator for SimpleEvaluator {
-    /// 估价
-    fn evaluate(&self, board: &Board, side: Side) -> f32 {
-        let mut sum = 0;
-        let mut side_sum = 0;
-        if board.get_piece_count() > 14 {
-            for x in 0..9 {
-                for y in 0..10 {
-                    let piece = board.get_piece_at((x, y));
-                    let score = SimpleEvaluator::evaluate_single_piece_1(piece);
-                    sum += score;
-                    if board.crossing_occupied_by_side((x, y), side) {
-                        side_sum += score;
-                    }
-                }
-            }
+    /// 以 `side` 的视角估价，即双方子力价值之差，残局时马的价值高于炮
+    fn evaluate(&self, board: &Board, side: Side) -> Score {
+        let evaluate_single_piece = if board.get_piece_count() > 14 {
+            SimpleEvaluator::evaluate_single_piece_1
         } else {
-            for x in 0..9 {
-                for y in 0..10 {
-                    let piece = board.get_piece_at((x, y));
-                    let score = SimpleEvaluator::evaluate_single_piece_2(piece);
-                    sum += score;
-                    if board.crossing_occupied_by_side((x, y), side) {
-                        side_sum += score;
-                    }
-                }
+            SimpleEvaluator::evaluate_single_piece_2
+        };
+        let mut ret = 0;
+        for pos in Square::all() {
+            let score = evaluate_single_piece(board.get_piece_at(pos));
+            if board.crossing_occupied_by_side(pos, side) {
+                ret += score;
+            } else {
+                ret -= score;
             }
         }
-        (side_sum as f32) / (sum as f32)
+        Score::new(ret)
+    }
+
+    /// 获取棋子的子力价值，按开局、中局计
+    fn piece_value(&self, piece: Piece) -> i32 {
+        SimpleEvaluator::evaluate_single_piece_1(Some(piece))
     }
 }
@@ -4,41 +4,142 @@
  */
 
 use crate::board::{Board, Move, Side};
-use crate::decider::Decider;
+use crate::decider::{Decider, Decision};
+use crate::search::{Clock, ProgressCallback, SearchResult};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 /// 游戏
 pub struct Game<RD: Decider, BD: Decider> {
     red_decider: RD,
     black_decider: BD,
-    on_move: fn(board: &Board, mov: &Move),
+    observers: Vec<Box<dyn GameObserver>>,
+    search_info: Arc<Mutex<Vec<(Side, SearchResult)>>>,
+    clock: Option<Clock>,
+    illegal_move_retries: u32,
 }
 
+/// 在后台线程中进行的游戏，见 [`Game::spawn`]
+pub struct GameHandle<RD: Decider, BD: Decider> {
+    handle: JoinHandle<(Game<RD, BD>, GameOutcome)>,
+}
+
+/// 对局事件的观察者
+///
+/// 观察者可以保存状态，例如写入文件、更新界面或统计数据。
+/// 可以向 [`Game`] 添加多个观察者，按添加的顺序通知。
+/// 游戏可能在后台线程中进行，因此观察者须可以跨线程传递。
+pub trait GameObserver: Send {
+    /// 走子后某方被将军，`board` 为走子之后的局面
+    fn on_check(&mut self, _board: &Board, _side: Side) {}
+
+    /// 对局结束
+    fn on_game_end(&mut self, _outcome: &GameOutcome) {}
+
+    /// 对局开始
+    fn on_game_start(&mut self, _board: &Board) {}
+
+    /// 某方走了一步，`board` 为走子之后的局面，`elapsed` 为作出决定的用时
+    fn on_move(&mut self, _board: &Board, _mov: &Move, _side: Side, _elapsed: Duration) {}
+
+    /// 某方的决定器完成一轮迭代加深，只有实现了 [`Decider::set_search_info_callback`] 的决定器会报告
+    ///
+    /// 搜索过程中只记录，该方作出决定后才按顺序一并通知，早于 [`GameObserver::on_move`]。
+    /// 需要实时显示搜索进度时，应直接为决定器设置进度回调，对局中仍会调用。
+    fn on_search_info(&mut self, _side: Side, _result: &SearchResult) {}
+}
+
+/// 对局结果
+#[derive(Debug, Clone)]
+pub struct GameOutcome {
+    /// 赢家，和棋时为 `None`
+    pub winner: Option<Side>,
+    /// 结束的原因
+    pub reason: TerminationReason,
+    /// 终局局面
+    pub board: Board,
+    /// 总步数
+    pub move_count: u32,
+    /// 因不合法或循环不变着而判负的决定
+    pub illegal_decision: Option<Decision>,
+}
+
+/// 对局结束的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminationReason {
+    /// 双方同意和棋
+    Agreement,
+    /// 将死，被将军的一方无论怎样走子帅 / 将都会被吃
+    Checkmate,
+    /// 按自然限着判和
+    DrawClaimed,
+    /// 帅 / 将被吃
+    GeneralCaptured,
+    /// 某方作出不合法的决定，包括不合法的走子、没有求和时接受和棋、未到自然限着时要求判和，按规则二判负
+    IllegalMove(Side),
+    /// 某方在循环后第五步不变着，按规则三判负
+    Repetition(Side),
+    /// 认输
+    Resignation,
+    /// 困毙，没有被将军的一方无子可走或无论怎样走子帅 / 将都会被吃
+    Stalemate,
+    /// 达到步数上限判和
+    StepLimit,
+    /// 某方超时
+    Timeout(Side),
+}
+
+/// 自然限着：连续多少步没有吃子后可以要求判和
+const NATURAL_LIMIT_PLY: u32 = 120;
+
 impl<RD: Decider, BD: Decider> Game<RD, BD> {
-    /// 绑定移动事件
-    pub fn bind_on_move(&mut self, slot: fn(board: &Board, mov: &Move,)) {
-        self.on_move = slot;
+    /// 添加观察者
+    pub fn add_observer<O: GameObserver + 'static>(&mut self, observer: O) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// 获取某方的决定器
+    fn decider_of(&mut self, side: Side) -> &mut dyn Decider {
+        match side {
+            Side::Red => &mut self.red_decider,
+            Side::Black => &mut self.black_decider,
+        }
     }
 
     /// 开始游戏
-    pub fn go(&self, board: &mut Board) -> Result<Option<Side>, ()> {
-        loop {
-            let red_decision = self.red_decider.make_decision(board, Side::Red).unwrap();
-            if board.apply_move(&red_decision).is_err() {
-                return Err(());
-            }
-            if board.game_finished() {
-                return Ok(board.get_winner());
+    ///
+    /// 开始前通知双方新的一局，每走一步通知对方，结束后通知双方结果。
+    /// 有观察者时通过 [`Decider::set_search_info_callback`] 收集双方的搜索信息以便通知观察者，
+    /// 对局结束后清除，决定器自己的进度回调不受影响。
+    pub fn go(&mut self, board: &mut Board) -> GameOutcome {
+        let observed = !self.observers.is_empty();
+        if observed {
+            for side in [Side::Red, Side::Black] {
+                let search_info = self.search_info.clone();
+                self.decider_of(side)
+                    .set_search_info_callback(Some(ProgressCallback::new(move |result| {
+                        search_info.lock().unwrap().push((side, result.clone()));
+                    })));
             }
-            (self.on_move)(board, &red_decision);
-            let black_decision = self.black_decider.make_decision(board, Side::Black).unwrap();
-            if board.apply_move(&black_decision).is_err() {
-                return Err(());
-            }
-            if board.game_finished() {
-                return Ok(board.get_winner());
-            }
-            (self.on_move)(board, &black_decision);
         }
+        self.red_decider.new_game(board, Side::Red);
+        self.black_decider.new_game(board, Side::Black);
+        self.notify(|observer| observer.on_game_start(board));
+        let outcome = self.play(board);
+        if self.clock.is_some() {
+            self.red_decider.set_clock(None);
+            self.black_decider.set_clock(None);
+        }
+        if observed {
+            self.red_decider.set_search_info_callback(None);
+            self.black_decider.set_search_info_callback(None);
+        }
+        self.red_decider.game_over(&outcome);
+        self.black_decider.game_over(&outcome);
+        self.notify(|observer| observer.on_game_end(&outcome));
+        outcome
     }
 
     /// 构造
@@ -46,7 +147,230 @@ impl<RD: Decider, BD: Decider> Game<RD, BD> {
         Self {
             red_decider,
             black_decider,
-            on_move: |_, _| {},
+            observers: Vec::new(),
+            search_info: Arc::new(Mutex::new(Vec::new())),
+            clock: None,
+            illegal_move_retries: 0,
         }
     }
+
+    /// 按添加的顺序通知所有观察者
+    fn notify<F: FnMut(&mut dyn GameObserver)>(&mut self, mut event: F) {
+        for observer in self.observers.iter_mut() {
+            event(observer.as_mut());
+        }
+    }
+
+    /// 双方轮流作出决定直到终局
+    ///
+    /// 和棋提议只在对方紧接着的下一次决定时有效。
+    fn play(&mut self, board: &mut Board) -> GameOutcome {
+        let mut side = Side::Red;
+        let mut draw_offered = false;
+        let mut clocks = [self.clock; 2];
+        let mut retries = [self.illegal_move_retries; 2];
+        loop {
+            if general_doomed(board, side) {
+                let reason = if board.general_threatened(side) {
+                    TerminationReason::Checkmate
+                } else {
+                    TerminationReason::Stalemate
+                };
+                return GameOutcome::new(board, Some(side.other()), reason);
+            }
+            let clock = &mut clocks[side as usize];
+            if let Some(clock) = clock {
+                self.decider_of(side).set_clock(Some(*clock));
+            }
+            let start = Instant::now();
+            let decider = self.decider_of(side);
+            let mut decision = decider.make_decision(board, side);
+            // 循环不变着直接判负，其余不合法的决定在重试次数内要求重新决定
+            while !is_legal(board, side, &decision, draw_offered)
+                && !is_repetition(board, &decision)
+                && retries[side as usize] > 0
+            {
+                retries[side as usize] -= 1;
+                decider.decision_rejected(board, &decision);
+                decision = decider.make_decision(board, side);
+            }
+            let elapsed = start.elapsed();
+            let search_info = std::mem::take(&mut *self.search_info.lock().unwrap());
+            for (side, result) in &search_info {
+                self.notify(|observer| observer.on_search_info(*side, result));
+            }
+            if let (Some(clock), Some(initial)) = (clock, self.clock) {
+                if !consume_time(clock, &initial, elapsed) {
+                    return GameOutcome::new(
+                        board,
+                        Some(side.other()),
+                        TerminationReason::Timeout(side),
+                    );
+                }
+            }
+            if !is_legal(board, side, &decision, draw_offered) {
+                let reason = if is_repetition(board, &decision) {
+                    TerminationReason::Repetition(side)
+                } else {
+                    TerminationReason::IllegalMove(side)
+                };
+                let mut ret = GameOutcome::new(board, Some(side.other()), reason);
+                ret.illegal_decision = Some(decision);
+                return ret;
+            }
+            let mov = match decision {
+                Decision::AcceptDraw => {
+                    return GameOutcome::new(board, None, TerminationReason::Agreement)
+                }
+                Decision::ClaimDraw => {
+                    return GameOutcome::new(board, None, TerminationReason::DrawClaimed)
+                }
+                Decision::Move(mov) | Decision::OfferDraw(mov) => mov,
+                Decision::Resign => {
+                    return GameOutcome::new(
+                        board,
+                        Some(side.other()),
+                        TerminationReason::Resignation,
+                    )
+                }
+            };
+            board.apply_move_unchecked(&mov);
+            self.notify(|observer| observer.on_move(board, &mov, side, elapsed));
+            if board.game_finished() {
+                let reason = match board.get_winner() {
+                    Some(_) => TerminationReason::GeneralCaptured,
+                    None => TerminationReason::StepLimit,
+                };
+                return GameOutcome::new(board, board.get_winner(), reason);
+            }
+            if board.general_threatened(side.other()) {
+                self.notify(|observer| observer.on_check(board, side.other()));
+            }
+            side = side.other();
+            draw_offered = matches!(decision, Decision::OfferDraw(_));
+            let opponent = self.decider_of(side);
+            opponent.opponent_moved(board, &mov);
+            if draw_offered {
+                opponent.draw_offered(board);
+            }
+        }
+    }
+
+    /// 设置双方的棋钟，`None` 表示不限时
+    ///
+    /// 设置后每次决定前以剩余时间设置该方决定器的棋钟，决定器原有的深度、节点数等限制不变，
+    /// 对局结束后清除。用完剩余时间的一方判负。
+    pub fn set_clock(&mut self, clock: Option<Clock>) {
+        self.clock = clock;
+    }
+
+    /// 设置每方每局可以重试不合法决定的次数，默认为 0，即不合法的决定直接判负
+    ///
+    /// 用于容忍有缺陷的决定器。循环不变着按规则三直接判负，不能重试。
+    pub fn set_illegal_move_retries(&mut self, retries: u32) {
+        self.illegal_move_retries = retries;
+    }
+
+    /// 设置双方是否在对方思考时后台思考
+    ///
+    /// 后台思考在决定器自己的线程中进行，不会阻塞游戏；
+    /// 要使游戏本身也不阻塞调用者，例如等待界面上的人类棋手走子时，用 [`Game::spawn`] 在后台线程中进行。
+    pub fn set_pondering(&mut self, pondering: bool) {
+        self.red_decider.set_pondering(pondering);
+        self.black_decider.set_pondering(pondering);
+    }
+
+    /// 设置总种子，由其为双方的决定器派生各自的种子，以便重现整盘对局
+    pub fn set_seed(&mut self, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.red_decider.set_seed(rng.gen());
+        self.black_decider.set_seed(rng.gen());
+    }
+
+    /// 在后台线程中开始游戏，不阻塞调用者
+    ///
+    /// 对局过程与 [`Game::go`] 相同，可以通过观察者跟踪进度，结束后由 [`GameHandle::join`] 取回游戏和结果。
+    pub fn spawn(mut self, mut board: Board) -> GameHandle<RD, BD>
+    where
+        RD: Send + 'static,
+        BD: Send + 'static,
+    {
+        let handle = thread::spawn(move || {
+            let outcome = self.go(&mut board);
+            (self, outcome)
+        });
+        GameHandle { handle }
+    }
+}
+
+impl<RD: Decider, BD: Decider> GameHandle<RD, BD> {
+    /// 查询对局是否已经结束
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// 等待对局结束，取回游戏和结果，终局局面见 [`GameOutcome::board`]
+    pub fn join(self) -> (Game<RD, BD>, GameOutcome) {
+        self.handle.join().unwrap()
+    }
+}
+
+impl GameOutcome {
+    /// 以终局局面构造
+    fn new(board: &Board, winner: Option<Side>, reason: TerminationReason) -> GameOutcome {
+        GameOutcome {
+            winner,
+            reason,
+            board: board.clone(),
+            move_count: board.get_move_count(),
+            illegal_decision: None,
+        }
+    }
+}
+
+/// 从棋钟扣除一步的用时并加时，返回是否仍有剩余时间
+fn consume_time(clock: &mut Clock, initial: &Clock, elapsed: Duration) -> bool {
+    if elapsed > clock.remaining {
+        return false;
+    }
+    clock.remaining = clock.remaining - elapsed + clock.increment;
+    if let Some(moves_to_go) = &mut clock.moves_to_go {
+        *moves_to_go = moves_to_go.saturating_sub(1);
+        if *moves_to_go == 0 {
+            clock.remaining += initial.remaining;
+            clock.moves_to_go = initial.moves_to_go;
+        }
+    }
+    true
+}
+
+/// 查询某方是否无论怎样走子，帅 / 将都会被吃
+fn general_doomed(board: &Board, side: Side) -> bool {
+    let mut board = board.clone();
+    let moves: Vec<Move> = board.query_possible_moves_of_side(side).collect();
+    moves.iter().all(|mov| {
+        board.apply_move_unchecked(mov);
+        let doomed = !board.game_finished() && board.general_threatened(side);
+        board.undo_move().unwrap();
+        doomed
+    })
+}
+
+/// 查询某方的决定是否合法，走子须走己方的棋子
+fn is_legal(board: &Board, side: Side, decision: &Decision, draw_offered: bool) -> bool {
+    match decision {
+        Decision::AcceptDraw => draw_offered,
+        Decision::ClaimDraw => board.get_ply_since_capture() >= NATURAL_LIMIT_PLY,
+        Decision::Move(mov) | Decision::OfferDraw(mov) => {
+            board.crossing_occupied_by_side(mov.pos_from, side) && board.check_move(mov)
+        }
+        Decision::Resign => true,
+    }
+}
+
+/// 查询决定是否为循环后的第五步不变着
+fn is_repetition(board: &Board, decision: &Decision) -> bool {
+    decision
+        .get_move()
+        .is_some_and(|mov| board.is_repetition(&mov))
 }
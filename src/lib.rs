@@ -6,7 +6,14 @@ pub mod board;
 pub mod decider;
 pub mod evaluator;
 pub mod game;
+pub mod mcts;
 pub mod prelude;
+pub mod score;
+pub mod search;
+pub mod skill;
+pub mod square;
+pub mod transposition;
+pub mod tree;
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,490 @@
+/*
+ * 模块 mcts，蒙特卡洛树搜索走子决定器。
+ * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
+ */
+
+use crate::{
+    board::{Board, Move, Side},
+    decider::{static_best_move, Decider, DeciderRng, Decision},
+    evaluator::Evaluator,
+    score::Score,
+    search::{Clock, SearchLimits, SearchResult, StopSignal, TimeManager},
+    transposition::TranspositionTable,
+};
+use rand::{prelude::*, rngs::StdRng};
+
+/// 蒙特卡洛树搜索决定器
+///
+/// 每次迭代从根节点按选择策略走到叶节点，展开后以模拟策略估计胜率，再沿路径回传。
+/// 搜索限制中的节点数即迭代次数，深度限制不起作用。最终选择访问次数最多的走法。
+#[derive(Debug)]
+pub struct MctsDecider<E: Evaluator> {
+    config: MctsConfig,
+    evaluator: E,
+    limits: SearchLimits,
+    rng: DeciderRng,
+    stop_signal: StopSignal,
+    tree: Option<MctsTree>,
+}
+
+/// 蒙特卡洛树搜索配置
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MctsConfig {
+    /// 选择策略
+    pub selection: SelectionPolicy,
+    /// 模拟策略
+    pub playout: PlayoutPolicy,
+    /// 是否在两步之间复用搜索树
+    pub reuse_tree: bool,
+}
+
+/// 选择策略
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionPolicy {
+    /// 上置信界，参数为探索系数
+    Uct(f32),
+    /// 带先验概率的上置信界，先验概率由评估器对走子后局面的估值得到，参数为探索系数
+    Puct(f32),
+}
+
+/// 模拟策略
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayoutPolicy {
+    /// 随机走子直到终局，超过给定步数仍未终局时由评估器估计胜率
+    Random {
+        /// 最多模拟的步数
+        max_plies: u32,
+    },
+    /// 不模拟，直接由评估器估计胜率
+    Evaluator,
+}
+
+/// 搜索树
+#[derive(Debug, Clone)]
+struct MctsTree {
+    nodes: Vec<MctsNode>,
+}
+
+/// 搜索树节点
+#[derive(Debug, Clone)]
+struct MctsNode {
+    /// 走到该节点的走法，根节点为 `None`
+    mov: Option<Move>,
+    /// 置换表键，用于复用搜索树时查找当前局面
+    key: u64,
+    /// 走子方
+    side: Side,
+    /// 子节点下标，未展开时为 `None`
+    children: Option<Vec<usize>>,
+    /// 先验概率
+    prior: f32,
+    /// 访问次数
+    visits: u32,
+    /// 以走到该节点的一方视角的累计胜率
+    value_sum: f64,
+}
+
+/// 由估值换算胜率时的尺度，估值高出这么多时胜率约为 73%
+const EVALUATION_SCALE: f32 = 100.0;
+
+/// 计算先验概率时的温度，单位与估值相同
+const PRIOR_TEMPERATURE: f32 = 50.0;
+
+/// 默认迭代次数
+const DEFAULT_ITERATIONS: u64 = 10000;
+
+impl<E: Evaluator> MctsDecider<E> {
+    /// 清空搜索树
+    pub fn clear_tree(&mut self) {
+        self.tree = None;
+    }
+
+    /// 展开节点，返回以该节点走子方视角的胜率估计
+    fn expand<R: Rng>(
+        &self,
+        tree: &mut MctsTree,
+        index: usize,
+        board: &mut Board,
+        rng: &mut R,
+    ) -> f64 {
+        let side = tree.nodes[index].side;
+        let steps: Vec<Move> = board.query_possible_moves_of_side(side).collect();
+        let priors: Vec<f32> = match self.config.selection {
+            SelectionPolicy::Uct(_) => vec![1.0; steps.len()],
+            SelectionPolicy::Puct(_) => {
+                let scores: Vec<f32> = steps
+                    .iter()
+                    .map(|step| {
+                        board.apply_move_unchecked(step);
+                        let score = self.evaluator.evaluate(board, side).get_value() as f32;
+                        board.undo_move().unwrap();
+                        score / PRIOR_TEMPERATURE
+                    })
+                    .collect();
+                let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                let weights: Vec<f32> = scores.iter().map(|score| (score - max).exp()).collect();
+                let sum: f32 = weights.iter().sum();
+                weights.iter().map(|weight| weight / sum).collect()
+            }
+        };
+        let mut children = Vec::with_capacity(steps.len());
+        for (step, prior) in steps.into_iter().zip(priors) {
+            board.apply_move_unchecked(&step);
+            children.push(tree.nodes.len());
+            tree.nodes.push(MctsNode {
+                mov: Some(step),
+                key: TranspositionTable::key(board.get_hash(), side.other()),
+                side: side.other(),
+                children: None,
+                prior,
+                visits: 0,
+                value_sum: 0.0,
+            });
+            board.undo_move().unwrap();
+        }
+        tree.nodes[index].children = Some(children);
+        self.playout(board, side, rng)
+    }
+
+    /// 获取配置
+    pub fn get_config(&self) -> &MctsConfig {
+        &self.config
+    }
+
+    /// 获取搜索限制
+    pub fn get_limits(&self) -> &SearchLimits {
+        &self.limits
+    }
+
+    /// 获取停止信号，可以复制后交给其他线程以中断搜索
+    pub fn get_stop_signal(&self) -> &StopSignal {
+        &self.stop_signal
+    }
+
+    /// 进行一次迭代，返回到达的深度
+    fn iterate<R: Rng>(&self, tree: &mut MctsTree, board: &mut Board, rng: &mut R) -> u32 {
+        let mut path = vec![0];
+        let mut index = 0;
+        let value = loop {
+            let node = &tree.nodes[index];
+            if board.game_finished() {
+                break terminal_value(board, node.side);
+            }
+            match &node.children {
+                None => break self.expand(tree, index, board, rng),
+                Some(children) if children.is_empty() => break 0.0,
+                Some(children) => {
+                    index = self.select(tree, index, children, rng);
+                    board.apply_move_unchecked(&tree.nodes[index].mov.unwrap());
+                    path.push(index);
+                }
+            }
+        };
+        // 回传时交替转换视角，节点中记录的是走到该节点一方的胜率
+        let mut value = value;
+        for &index in path.iter().rev() {
+            let node = &mut tree.nodes[index];
+            node.visits += 1;
+            node.value_sum += 1.0 - value;
+            value = 1.0 - value;
+        }
+        for _ in 1..path.len() {
+            board.undo_move().unwrap();
+        }
+        path.len() as u32 - 1
+    }
+
+    /// 以迭代次数限制构造
+    pub fn new(evaluator: E, iterations: u32) -> MctsDecider<E> {
+        MctsDecider::with_limits(evaluator, SearchLimits::nodes(iterations as u64))
+    }
+
+    /// 模拟，返回以 `side` 视角的胜率估计
+    fn playout<R: Rng>(&self, board: &mut Board, side: Side, rng: &mut R) -> f64 {
+        let max_plies = match self.config.playout {
+            PlayoutPolicy::Random { max_plies } => max_plies,
+            PlayoutPolicy::Evaluator => 0,
+        };
+        let mut current = side;
+        let mut plies = 0;
+        while plies < max_plies && !board.game_finished() {
+            let steps: Vec<Move> = board.query_possible_moves_of_side(current).collect();
+            match steps.choose(rng) {
+                Some(step) => board.apply_move_unchecked(step),
+                None => break,
+            }
+            plies += 1;
+            current = current.other();
+        }
+        let value = if board.game_finished() {
+            terminal_value(board, side)
+        } else if board.query_possible_moves_of_side(current).next().is_none() {
+            // 无子可走，判负
+            if current == side {
+                0.0
+            } else {
+                1.0
+            }
+        } else {
+            win_probability(self.evaluator.evaluate(board, side))
+        };
+        for _ in 0..plies {
+            board.undo_move().unwrap();
+        }
+        value
+    }
+
+    /// 取出以当前局面为根的搜索树，能复用上一次的子树时复用之
+    ///
+    /// 在上一次的根节点之后两步以内查找当前局面，找到时以其为根重建搜索树。
+    fn take_tree(&mut self, board: &Board, side: Side) -> MctsTree {
+        let key = TranspositionTable::key(board.get_hash(), side);
+        let previous = self.tree.take();
+        if let Some(previous) = previous.filter(|_| self.config.reuse_tree) {
+            let mut candidates = vec![0];
+            for _ in 0..=2 {
+                if let Some(&found) = candidates
+                    .iter()
+                    .find(|&&index| previous.nodes[index].key == key)
+                {
+                    return previous.subtree(found);
+                }
+                candidates = candidates
+                    .iter()
+                    .filter_map(|&index| previous.nodes[index].children.as_ref())
+                    .flatten()
+                    .copied()
+                    .collect();
+            }
+        }
+        MctsTree {
+            nodes: vec![MctsNode {
+                mov: None,
+                key,
+                side,
+                children: None,
+                prior: 1.0,
+                visits: 0,
+                value_sum: 0.0,
+            }],
+        }
+    }
+
+    /// 选择子节点
+    fn select<R: Rng>(
+        &self,
+        tree: &MctsTree,
+        parent: usize,
+        children: &[usize],
+        rng: &mut R,
+    ) -> usize {
+        let parent_visits = tree.nodes[parent].visits.max(1) as f64;
+        let mut best = children[0];
+        let mut best_value = f64::NEG_INFINITY;
+        for &child in children {
+            let node = &tree.nodes[child];
+            let exploitation = if node.visits == 0 {
+                0.5
+            } else {
+                node.value_sum / node.visits as f64
+            };
+            let value = match self.config.selection {
+                SelectionPolicy::Uct(exploration) => {
+                    if node.visits == 0 {
+                        f64::INFINITY
+                    } else {
+                        exploitation
+                            + exploration as f64 * (parent_visits.ln() / node.visits as f64).sqrt()
+                    }
+                }
+                SelectionPolicy::Puct(exploration) => {
+                    exploitation
+                        + exploration as f64 * node.prior as f64 * parent_visits.sqrt()
+                            / (1 + node.visits) as f64
+                }
+            };
+            // 同分时随机选择
+            if value > best_value || (value == best_value && rng.gen_bool(0.5)) {
+                best = child;
+                best_value = value;
+            }
+        }
+        best
+    }
+
+    /// 设置配置
+    pub fn set_config(&mut self, config: MctsConfig) {
+        self.config = config;
+    }
+
+    /// 以搜索限制构造
+    pub fn with_limits(evaluator: E, limits: SearchLimits) -> MctsDecider<E> {
+        MctsDecider {
+            config: MctsConfig::default(),
+            evaluator,
+            limits,
+            rng: DeciderRng::new(),
+            stop_signal: StopSignal::new(),
+            tree: None,
+        }
+    }
+}
+
+impl MctsTree {
+    /// 获取从某节点开始不断选择访问次数最多的子节点得到的走法序列
+    fn principal_variation(&self, index: usize) -> Vec<Move> {
+        let mut ret = Vec::new();
+        let mut current = index;
+        while let Some(child) = self.nodes[current]
+            .children
+            .as_ref()
+            .and_then(|children| {
+                children
+                    .iter()
+                    .max_by_key(|&&child| self.nodes[child].visits)
+            })
+            .filter(|&&child| self.nodes[child].visits > 0)
+        {
+            ret.push(self.nodes[*child].mov.unwrap());
+            current = *child;
+        }
+        ret
+    }
+
+    /// 以某节点为根复制子树
+    fn subtree(&self, root: usize) -> MctsTree {
+        let mut nodes = vec![MctsNode {
+            mov: None,
+            ..self.nodes[root].clone()
+        }];
+        let mut queue = vec![(root, 0)];
+        while let Some((old, new)) = queue.pop() {
+            if let Some(children) = &self.nodes[old].children {
+                let mut new_children = Vec::with_capacity(children.len());
+                for &child in children {
+                    new_children.push(nodes.len());
+                    queue.push((child, nodes.len()));
+                    nodes.push(self.nodes[child].clone());
+                }
+                nodes[new].children = Some(new_children);
+            }
+        }
+        MctsTree { nodes }
+    }
+}
+
+impl Default for MctsConfig {
+    /// 上置信界选择，随机模拟至多 40 步，复用搜索树
+    fn default() -> MctsConfig {
+        MctsConfig {
+            selection: SelectionPolicy::Uct(1.4),
+            playout: PlayoutPolicy::Random { max_plies: 40 },
+            reuse_tree: true,
+        }
+    }
+}
+
+impl<E: Evaluator> Decider for MctsDecider<E> {
+    /// 作出走子决定，一次迭代都没有完成时按走子后的静态估值选择，无子可走时认输
+    fn make_decision(&mut self, board: &Board, side: Side) -> Decision {
+        self.search(board, side)
+            .best_move
+            .or_else(|| static_best_move(&self.evaluator, board, side))
+            .into()
+    }
+
+    /// 新的一局开始，清空搜索树
+    fn new_game(&mut self, _board: &Board, _side: Side) {
+        self.clear_tree();
+    }
+
+    /// 在搜索限制内迭代，返回访问次数最多的走法、由其胜率换算的分数和主要变例
+    ///
+    /// 一次迭代都没有完成时结果中没有走法。
+    fn search(&mut self, board: &Board, side: Side) -> SearchResult {
+        let time_manager = TimeManager::new(&self.limits);
+        let max_iterations = match (self.limits.nodes, self.limits.infinite) {
+            (Some(nodes), _) => nodes,
+            (None, true) => u64::MAX,
+            (None, false) if time_manager.get_soft_limit().is_some() => u64::MAX,
+            (None, false) => DEFAULT_ITERATIONS,
+        };
+        let mut tree = self.take_tree(board, side);
+        let mut playground = board.clone();
+        let mut rng: StdRng = self.rng.fork();
+        let mut iterations = 0;
+        let mut depth = 0;
+        while iterations < max_iterations
+            && !self.stop_signal.is_stopped()
+            && time_manager
+                .get_soft_limit()
+                .is_none_or(|soft_limit| time_manager.elapsed() < soft_limit)
+        {
+            depth = depth.max(self.iterate(&mut tree, &mut playground, &mut rng));
+            iterations += 1;
+        }
+        let principal_variation = tree.principal_variation(0);
+        let score = principal_variation.first().map_or(Score::DRAW, |_| {
+            let best = tree.nodes[0]
+                .children
+                .iter()
+                .flatten()
+                .max_by_key(|&&child| tree.nodes[child].visits)
+                .unwrap();
+            let node = &tree.nodes[*best];
+            evaluation_from_probability(node.value_sum / node.visits as f64)
+        });
+        let ret = SearchResult {
+            best_move: principal_variation.first().copied(),
+            score: score.into(),
+            depth,
+            node_count: iterations,
+            elapsed: time_manager.elapsed(),
+            ponder_move: principal_variation.get(1).copied(),
+            principal_variation,
+            ..SearchResult::default()
+        };
+        self.tree = Some(tree);
+        ret
+    }
+
+    /// 设置棋钟，与其余搜索限制一起生效
+    fn set_clock(&mut self, clock: Option<Clock>) {
+        self.limits.clock = clock;
+    }
+
+    /// 设置搜索限制
+    fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    /// 设置随机数种子，用于模拟和在同分时随机选择
+    fn set_seed(&mut self, seed: u64) {
+        self.rng.set_seed(seed);
+    }
+
+    /// 设置停止信号
+    fn set_stop_signal(&mut self, signal: StopSignal) {
+        self.stop_signal = signal;
+    }
+}
+
+/// 由胜率换算估值，即 [`win_probability`] 的反函数
+fn evaluation_from_probability(probability: f64) -> Score {
+    let probability = probability.clamp(1e-6, 1.0 - 1e-6);
+    Score::new((EVALUATION_SCALE as f64 * (probability / (1.0 - probability)).ln()).round() as i32)
+}
+
+/// 游戏结束时以 `side` 视角的胜率
+fn terminal_value(board: &Board, side: Side) -> f64 {
+    match board.get_winner() {
+        Some(winner) if winner == side => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    }
+}
+
+/// 由估值换算胜率
+fn win_probability(score: Score) -> f64 {
+    1.0 / (1.0 + (-(score.get_value() as f64) / EVALUATION_SCALE as f64).exp())
+}
@@ -3,7 +3,15 @@
  * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
  */
 
-pub use crate::board::{Board, Move, Piece, PieceKind, Side};
-pub use crate::decider::{Decider, MaxMinDecider, RandomDecider};
+pub use crate::board::{Board, HistoryError, Move, MoveRecord, Piece, PieceKind, Side};
+pub use crate::decider::{Decider, Decision, MaxMinDecider, RandomDecider};
 pub use crate::evaluator::{Evaluator, SimpleEvaluator};
-pub use crate::game::Game;
+pub use crate::game::{Game, GameHandle, GameObserver, GameOutcome, TerminationReason};
+pub use crate::mcts::{MctsConfig, MctsDecider, PlayoutPolicy, SelectionPolicy};
+pub use crate::score::Score;
+pub use crate::search::{
+    Clock, ProgressCallback, SearchConfig, SearchLimits, SearchResult, SearchScore, StopSignal,
+};
+pub use crate::skill::SkillDecider;
+pub use crate::square::Square;
+pub use crate::tree::{GameNode, GameTree, NodeId, TreeError};
@@ -0,0 +1,128 @@
+/*
+ * 模块 score，以走子方视角的整数分数。
+ * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
+ */
+
+use std::fmt;
+use std::ops::{Add, Neg, Sub};
+
+/// 以走子方视角的整数分数，正数表示对走子方有利
+///
+/// 局面估值与棋子的子力价值单位相同，限制在 [`Score::MAX_EVALUATION`] 以内。
+/// 绝对值更大的部分保留给杀棋分数：在第 n 层将死对方为 `MATE - n`，被将死为 `n - MATE`，
+/// 因此越快的胜利分数越高。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Score(i32);
+
+/// 杀棋分数范围内最多可以表示的层数
+const MAX_MATE_PLY: i32 = 1000;
+
+impl Score {
+    /// 均势
+    pub const DRAW: Score = Score(0);
+
+    /// 比任何分数都高的分数，用作搜索窗口的边界
+    pub const INFINITY: Score = Score(32000);
+
+    /// 立即将死对方的分数
+    pub const MATE: Score = Score(30000);
+
+    /// 局面估值的最大绝对值
+    pub const MAX_EVALUATION: Score = Score(Score::MATE.0 - MAX_MATE_PLY - 1);
+
+    /// 由数值直接构造，不作截断，用于还原由 [`Score::get_value`] 得到的数值
+    pub(crate) fn from_raw(value: i32) -> Score {
+        Score(value)
+    }
+
+    /// 将置换表中与层数无关的杀棋分数转换为第 `ply` 层的分数
+    pub fn from_table(self, ply: u32) -> Score {
+        if self.is_mate() {
+            Score(self.0 - self.0.signum() * ply as i32)
+        } else {
+            self
+        }
+    }
+
+    /// 获取若干回合后将死对方，负数表示若干回合后被对方将死，不是杀棋分数时为 `None`
+    pub fn get_mate_moves(self) -> Option<i32> {
+        if self.is_mate() {
+            let moves = (Score::MATE.0 - self.0.abs() + 1) / 2;
+            Some(moves * self.0.signum())
+        } else {
+            None
+        }
+    }
+
+    /// 获取分数的数值
+    pub fn get_value(self) -> i32 {
+        self.0
+    }
+
+    /// 查询是否为杀棋分数
+    pub fn is_mate(self) -> bool {
+        self.0.abs() > Score::MAX_EVALUATION.0 && self.0.abs() <= Score::MATE.0
+    }
+
+    /// 在第 `ply` 层将死对方的分数
+    pub fn mate_in(ply: u32) -> Score {
+        Score(Score::MATE.0 - (ply as i32).min(MAX_MATE_PLY))
+    }
+
+    /// 在第 `ply` 层被对方将死的分数
+    pub fn mated_in(ply: u32) -> Score {
+        -Score::mate_in(ply)
+    }
+
+    /// 由局面估值构造，超出范围时截断
+    pub fn new(value: i32) -> Score {
+        Score(value.clamp(-Score::MAX_EVALUATION.0, Score::MAX_EVALUATION.0))
+    }
+
+    /// 将第 `ply` 层的杀棋分数转换为与层数无关的形式，以便存入置换表
+    pub fn to_table(self, ply: u32) -> Score {
+        if self.is_mate() {
+            Score(self.0 + self.0.signum() * ply as i32)
+        } else {
+            self
+        }
+    }
+}
+
+impl Add<i32> for Score {
+    type Output = Score;
+
+    /// 加上一个余量
+    fn add(self, rhs: i32) -> Score {
+        Score(self.0 + rhs)
+    }
+}
+
+impl fmt::Display for Score {
+    /// 杀棋分数输出为“杀 n”或“被杀 n”，其余输出数值
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get_mate_moves() {
+            Some(moves) if moves > 0 => write!(f, "杀 {}", moves),
+            Some(moves) => write!(f, "被杀 {}", -moves),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    /// 转换为对方视角
+    fn neg(self) -> Score {
+        Score(-self.0)
+    }
+}
+
+impl Sub<i32> for Score {
+    type Output = Score;
+
+    /// 减去一个余量
+    fn sub(self, rhs: i32) -> Score {
+        Score(self.0 - rhs)
+    }
+}
@@ -0,0 +1,335 @@
+/*
+ * 模块 search，搜索限制、时间管理、停止信号和搜索结果等搜索相关的类型。
+ * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
+ */
+
+use crate::{board::Move, score::Score};
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+
+/// 搜索限制
+///
+/// 各项限制可以同时设置，任意一项达到即停止搜索。`infinite` 为真时忽略所有时间限制，
+/// 只受深度和节点数限制。所有限制都未设置时同样不限时间。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchLimits {
+    /// 最大搜索深度
+    pub depth: Option<u32>,
+    /// 最大节点数
+    pub nodes: Option<u64>,
+    /// 每步固定用时
+    pub move_time: Option<Duration>,
+    /// 棋钟
+    pub clock: Option<Clock>,
+    /// 是否无限搜索
+    pub infinite: bool,
+}
+
+/// 棋钟
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    /// 剩余时间
+    pub remaining: Duration,
+    /// 每走一步增加的时间
+    pub increment: Duration,
+    /// 距离下一次加时还需走的步数，`None` 表示剩余时间需用到终局
+    pub moves_to_go: Option<u32>,
+}
+
+/// 选择性搜索配置，每项技术都可以单独开关，以便在自我对局中衡量其效果
+///
+/// 估值余量与棋子的子力价值单位相同，默认值按 [`crate::evaluator::SimpleEvaluator`] 设定。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchConfig {
+    /// 空着剪枝，只剩兵、士、相时不使用以免误判等着
+    pub null_move: bool,
+    /// 后期走法衰减
+    pub late_move_reduction: bool,
+    /// 前沿节点剪枝
+    pub futility_pruning: bool,
+    /// 剃刀剪枝
+    pub razoring: bool,
+    /// 将军延伸
+    pub check_extension: bool,
+    /// 前沿节点剪枝的估值余量，按剩余深度成倍增加
+    pub futility_margin: i32,
+    /// 剃刀剪枝的估值余量，按剩余深度成倍增加
+    pub razoring_margin: i32,
+}
+
+/// 搜索结果
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchResult {
+    /// 最佳走法
+    pub best_move: Option<Move>,
+    /// 以走子方视角的分数
+    pub score: SearchScore,
+    /// 完成的搜索深度
+    pub depth: u32,
+    /// 搜索的节点数
+    pub node_count: u64,
+    /// 用时
+    pub elapsed: Duration,
+    /// 主要变例，第一步即最佳走法
+    pub principal_variation: Vec<Move>,
+    /// 预计对方的应着，可用于后台思考
+    pub ponder_move: Option<Move>,
+    /// 发生截断的节点数
+    pub cutoff_count: u64,
+    /// 第一个走法即引起截断的节点数，与截断节点数之比反映走法排序的质量
+    pub first_move_cutoff_count: u64,
+}
+
+/// 以走子方视角的分数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchScore {
+    /// 局面估值
+    Evaluation(i32),
+    /// 若干回合后将死对方，负数表示若干回合后被对方将死
+    Mate(i32),
+}
+
+/// 搜索进度回调，每完成一轮迭代加深调用一次
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(&SearchResult) + Send + Sync>);
+
+/// 停止信号
+///
+/// 可以在多个线程间共享，发出后正在进行的搜索会尽快停止，并返回目前找到的最佳走法。
+/// 搜索不会复位信号，因此在搜索开始前发出的信号同样有效，之后的搜索也会立即停止，
+/// 直到信号的持有者调用 [`StopSignal::reset`]。
+#[derive(Debug, Clone, Default)]
+pub struct StopSignal(Arc<AtomicBool>);
+
+/// 时间管理器，按搜索限制为一步棋分配时间
+///
+/// 软限制决定是否开始新一轮迭代加深，硬限制决定是否中断正在进行的搜索。
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    soft_limit: Option<Duration>,
+    hard_limit: Option<Duration>,
+}
+
+/// 未指定剩余步数时假定的剩余步数
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// 为避免超时而预留的时间
+const TIME_SAFETY_MARGIN: Duration = Duration::from_millis(50);
+
+impl SearchLimits {
+    /// 只限制棋钟
+    pub fn clock(remaining: Duration, increment: Duration) -> SearchLimits {
+        SearchLimits {
+            clock: Some(Clock {
+                remaining,
+                increment,
+                moves_to_go: None,
+            }),
+            ..SearchLimits::default()
+        }
+    }
+
+    /// 只限制深度
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    /// 无限搜索
+    pub fn infinite() -> SearchLimits {
+        SearchLimits {
+            infinite: true,
+            ..SearchLimits::default()
+        }
+    }
+
+    /// 只限制每步用时
+    pub fn move_time(move_time: Duration) -> SearchLimits {
+        SearchLimits {
+            move_time: Some(move_time),
+            ..SearchLimits::default()
+        }
+    }
+
+    /// 只限制节点数
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..SearchLimits::default()
+        }
+    }
+}
+
+impl SearchConfig {
+    /// 关闭所有选择性搜索技术
+    pub fn none() -> SearchConfig {
+        SearchConfig {
+            null_move: false,
+            late_move_reduction: false,
+            futility_pruning: false,
+            razoring: false,
+            check_extension: false,
+            ..SearchConfig::default()
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    /// 开启所有选择性搜索技术
+    fn default() -> SearchConfig {
+        SearchConfig {
+            null_move: true,
+            late_move_reduction: true,
+            futility_pruning: true,
+            razoring: true,
+            check_extension: true,
+            futility_margin: 50,
+            razoring_margin: 100,
+        }
+    }
+}
+
+impl ProgressCallback {
+    /// 调用
+    pub fn call(&self, result: &SearchResult) {
+        (self.0)(result)
+    }
+
+    /// 构造
+    pub fn new<F: Fn(&SearchResult) + Send + Sync + 'static>(callback: F) -> ProgressCallback {
+        ProgressCallback(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    /// 闭包无法输出，只输出类型名
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+impl SearchResult {
+    /// 获取第一个走法即引起截断的比例，没有发生截断时为 0
+    pub fn get_first_move_cutoff_rate(&self) -> f32 {
+        if self.cutoff_count == 0 {
+            0.0
+        } else {
+            self.first_move_cutoff_count as f32 / self.cutoff_count as f32
+        }
+    }
+}
+
+impl Default for SearchScore {
+    /// 均势
+    fn default() -> SearchScore {
+        SearchScore::Evaluation(0)
+    }
+}
+
+impl From<Score> for SearchScore {
+    /// 杀棋分数转换为回合数，其余转换为估值
+    fn from(score: Score) -> SearchScore {
+        match score.get_mate_moves() {
+            Some(moves) => SearchScore::Mate(moves),
+            None => SearchScore::Evaluation(score.get_value()),
+        }
+    }
+}
+
+impl StopSignal {
+    /// 查询是否已发出停止信号
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// 构造
+    pub fn new() -> StopSignal {
+        StopSignal::default()
+    }
+
+    /// 复位
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    /// 发出停止信号
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl From<Arc<AtomicBool>> for StopSignal {
+    /// 使用已有的共享标志，标志为真表示停止
+    fn from(flag: Arc<AtomicBool>) -> StopSignal {
+        StopSignal(flag)
+    }
+}
+
+impl TimeManager {
+    /// 自开始以来经过的时间
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// 获取硬限制
+    pub fn get_hard_limit(&self) -> Option<Duration> {
+        self.hard_limit
+    }
+
+    /// 获取软限制
+    pub fn get_soft_limit(&self) -> Option<Duration> {
+        self.soft_limit
+    }
+
+    /// 从现在开始计时，并按搜索限制分配时间
+    ///
+    /// 固定用时时软硬限制相同。使用棋钟时，平均分配剩余时间并计入大部分加时作为软限制，
+    /// 硬限制为软限制的三倍，但不超过剩余时间的一半。
+    pub fn new(limits: &SearchLimits) -> TimeManager {
+        let mut soft_limit = None;
+        let mut hard_limit = None;
+        if !limits.infinite {
+            if let Some(clock) = limits.clock {
+                let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                let available = clock.remaining.saturating_sub(TIME_SAFETY_MARGIN);
+                let base = available / moves_to_go + clock.increment * 3 / 4;
+                let soft = base.min(available);
+                soft_limit = Some(soft);
+                hard_limit = Some((soft * 3).min(available / 2).max(soft));
+            }
+            if let Some(move_time) = limits.move_time {
+                let move_time = move_time
+                    .saturating_sub(TIME_SAFETY_MARGIN)
+                    .max(move_time / 2);
+                soft_limit = Some(soft_limit.map_or(move_time, |soft| soft.min(move_time)));
+                hard_limit = Some(hard_limit.map_or(move_time, |hard| hard.min(move_time)));
+            }
+        }
+        TimeManager {
+            start: Instant::now(),
+            soft_limit,
+            hard_limit,
+        }
+    }
+
+    /// 查询是否已超过硬限制，需要立即中断搜索
+    pub fn out_of_time(&self) -> bool {
+        self.hard_limit
+            .is_some_and(|hard_limit| self.elapsed() >= hard_limit)
+    }
+
+    /// 查询是否还可以开始新一轮迭代
+    ///
+    /// 新一轮迭代通常比之前所有迭代加起来还要耗时，因此过了软限制的一半就不再开始。
+    pub fn should_start_iteration(&self) -> bool {
+        self.soft_limit
+            .is_none_or(|soft_limit| self.elapsed() < soft_limit / 2)
+    }
+}
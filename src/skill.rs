@@ -0,0 +1,170 @@
+/*
+ * 模块 skill，按等级限制棋力的走子决定器。
+ * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
+ */
+
+use crate::{
+    board::{Board, Move, Side},
+    decider::{static_best_move, Decider, DeciderRng, Decision, MaxMinDecider},
+    evaluator::Evaluator,
+    game::GameOutcome,
+    score::Score,
+    search::{Clock, ProgressCallback, SearchLimits, SearchResult, SearchScore, StopSignal},
+};
+use rand::prelude::*;
+
+/// 按等级限制棋力的决定器
+///
+/// 等级从 [`SkillDecider::MIN_LEVEL`] 到 [`SkillDecider::MAX_LEVEL`]。等级越低，
+/// 搜索的深度和节点数越少，并越常从多变例分析的候选中选择较差的走法，
+/// 但损失不超过随等级减小而增大的上限，以免出现不像人的大漏着。最高等级总是选择最佳走法。
+/// 不支持后台思考，忽略 [`Decider::set_pondering`]。
+///
+/// 各常量由自我对局校准（见测试 `skill_calibration`），相隔四级的十盘对局中高等级胜七到十盘。
+#[derive(Debug)]
+pub struct SkillDecider<E: Evaluator> {
+    decider: MaxMinDecider<E>,
+    level: u8,
+    rng: DeciderRng,
+}
+
+/// 多变例分析的候选个数
+const CANDIDATE_COUNT: usize = 4;
+
+/// 最低等级时选择较差走法的概率，取 0.6 时 20 级对 16 级只胜六盘
+const MAX_MISTAKE_PROBABILITY: f64 = 0.8;
+
+/// 每低一级，允许较差走法多损失的分数
+const LOSS_PER_LEVEL: i32 = 16;
+
+/// 每高一级，节点数限制按等级的平方增加的系数
+const NODES_PER_LEVEL_SQUARED: u64 = 250;
+
+impl<E: Evaluator + Send + Sync + 'static> SkillDecider<E> {
+    /// 最高等级
+    pub const MAX_LEVEL: u8 = 20;
+
+    /// 最低等级
+    pub const MIN_LEVEL: u8 = 1;
+
+    /// 从候选中按等级选择一个
+    fn choose(&self, mut lines: Vec<SearchResult>) -> SearchResult {
+        let Some(best_value) = lines.first().map(|line| comparable_value(line.score)) else {
+            return SearchResult::default();
+        };
+        let mut rng = self.rng.fork();
+        let mistake_probability = MAX_MISTAKE_PROBABILITY
+            * (SkillDecider::<E>::MAX_LEVEL - self.level) as f64
+            / (SkillDecider::<E>::MAX_LEVEL - SkillDecider::<E>::MIN_LEVEL) as f64;
+        if !rng.gen_bool(mistake_probability) {
+            return lines.swap_remove(0);
+        }
+        let max_loss = (SkillDecider::<E>::MAX_LEVEL - self.level) as i32 * LOSS_PER_LEVEL;
+        let candidates: Vec<usize> = (0..lines.len())
+            .filter(|&index| best_value - comparable_value(lines[index].score) <= max_loss)
+            .collect();
+        let index = *candidates.choose(&mut rng).unwrap_or(&0);
+        lines.swap_remove(index)
+    }
+
+    /// 获取等级
+    pub fn get_level(&self) -> u8 {
+        self.level
+    }
+
+    /// 按等级得到搜索限制
+    ///
+    /// 深度限制从 1 层到 6 层，节点数限制与等级的平方成正比。
+    pub fn level_limits(level: u8) -> SearchLimits {
+        let level = level.clamp(SkillDecider::<E>::MIN_LEVEL, SkillDecider::<E>::MAX_LEVEL);
+        SearchLimits {
+            depth: Some(1 + level as u32 / 4),
+            nodes: Some(NODES_PER_LEVEL_SQUARED * level as u64 * level as u64),
+            ..SearchLimits::default()
+        }
+    }
+
+    /// 以某等级构造，等级超出范围时取最接近的等级
+    pub fn new(evaluator: E, level: u8) -> SkillDecider<E> {
+        let mut ret = SkillDecider {
+            decider: MaxMinDecider::with_limits(evaluator, SearchLimits::default()),
+            level: 0,
+            rng: DeciderRng::new(),
+        };
+        ret.set_level(level);
+        ret
+    }
+
+    /// 设置等级，等级超出范围时取最接近的等级
+    pub fn set_level(&mut self, level: u8) {
+        self.level = level.clamp(SkillDecider::<E>::MIN_LEVEL, SkillDecider::<E>::MAX_LEVEL);
+        self.decider
+            .set_limits(SkillDecider::<E>::level_limits(self.level));
+    }
+}
+
+impl<E: Evaluator + Send + Sync + 'static> Decider for SkillDecider<E> {
+    /// 一局结束
+    fn game_over(&mut self, outcome: &GameOutcome) {
+        self.decider.game_over(outcome);
+    }
+
+    /// 作出走子决定，一步都没有搜完时按走子后的静态估值选择，无子可走时认输
+    fn make_decision(&mut self, board: &Board, side: Side) -> Decision {
+        self.search(board, side)
+            .best_move
+            .or_else(|| static_best_move(self.decider.get_evaluator(), board, side))
+            .into()
+    }
+
+    /// 新的一局开始
+    fn new_game(&mut self, board: &Board, side: Side) {
+        self.decider.new_game(board, side);
+    }
+
+    /// 对方走了一步
+    fn opponent_moved(&mut self, board: &Board, mov: &Move) {
+        self.decider.opponent_moved(board, mov);
+    }
+
+    /// 作出走子决定，返回所选候选的分数和主要变例，一步都没有搜完时结果中没有走法
+    fn search(&mut self, board: &Board, side: Side) -> SearchResult {
+        let lines = self.decider.search_multi_pv(board, side, CANDIDATE_COUNT);
+        self.choose(lines)
+    }
+
+    /// 设置棋钟，与棋力等级决定的深度和节点数限制一起生效
+    fn set_clock(&mut self, clock: Option<Clock>) {
+        self.decider.set_clock(clock);
+    }
+
+    /// 设置进度回调，每完成一轮迭代加深对每个候选调用一次
+    fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.decider.set_progress_callback(callback);
+    }
+
+    /// 设置向对局观察者报告搜索信息的回调，与进度回调在同样的时机调用
+    fn set_search_info_callback(&mut self, callback: Option<ProgressCallback>) {
+        self.decider.set_search_info_callback(callback);
+    }
+
+    /// 设置随机数种子，用于搜索和选择较差的走法
+    fn set_seed(&mut self, seed: u64) {
+        self.rng.set_seed(seed);
+        self.decider.set_seed(self.rng.fork().gen());
+    }
+
+    /// 设置停止信号
+    fn set_stop_signal(&mut self, signal: StopSignal) {
+        self.decider.set_stop_signal(signal);
+    }
+}
+
+/// 将分数转换为可以比较大小的数值，杀棋分数按回合数换算
+fn comparable_value(score: SearchScore) -> i32 {
+    match score {
+        SearchScore::Evaluation(value) => value,
+        SearchScore::Mate(moves) if moves > 0 => Score::mate_in(moves as u32 * 2 - 1).get_value(),
+        SearchScore::Mate(moves) => Score::mated_in(-moves as u32 * 2).get_value(),
+    }
+}
@@ -0,0 +1,128 @@
+/*
+ * 模块 square，棋盘上的位置。
+ * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
+ */
+
+use crate::board::Side;
+use std::fmt;
+use std::str::FromStr;
+
+/// 棋盘上的位置
+///
+/// 横坐标（路）取值 0 到 8，纵坐标（线）取值 0 到 9，红方底线为 0。
+/// 内部以 `路 * 10 + 线` 作为 0 到 89 的序号保存，因此不可能表示棋盘外的位置。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square {
+    /// 棋盘上位置的总数
+    pub const COUNT: usize = 90;
+
+    /// 遍历棋盘上的所有位置，按序号从小到大
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..Square::COUNT as u8).map(Square)
+    }
+
+    /// 查询是否已过河（位于某方的对方半场）
+    pub fn crossed_river(self, side: Side) -> bool {
+        !self.on_own_side(side)
+    }
+
+    /// 获取横坐标（路）
+    pub fn file(self) -> i32 {
+        (self.0 / 10) as i32
+    }
+
+    /// 按序号构造，序号超出范围时返回 `None`
+    pub fn from_index(index: usize) -> Option<Square> {
+        if index < Square::COUNT {
+            Some(Square(index as u8))
+        } else {
+            None
+        }
+    }
+
+    /// 查询是否在某方的九宫内
+    pub fn in_palace(self, side: Side) -> bool {
+        let (file, rank) = (self.file(), self.rank());
+        let ranks = match side {
+            Side::Red => 0..=2,
+            Side::Black => 7..=9,
+        };
+        (3..=5).contains(&file) && ranks.contains(&rank)
+    }
+
+    /// 获取序号
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// 按坐标构造，坐标在棋盘外时返回 `None`
+    pub fn new(file: i32, rank: i32) -> Option<Square> {
+        if (0..9).contains(&file) && (0..10).contains(&rank) {
+            Some(Square((file * 10 + rank) as u8))
+        } else {
+            None
+        }
+    }
+
+    /// 按偏移量获取另一位置，超出棋盘时返回 `None`
+    pub fn offset(self, file_offset: i32, rank_offset: i32) -> Option<Square> {
+        Square::new(self.file() + file_offset, self.rank() + rank_offset)
+    }
+
+    /// 查询是否位于某方本方半场（河界以内）
+    pub fn on_own_side(self, side: Side) -> bool {
+        match side {
+            Side::Red => self.rank() <= 4,
+            Side::Black => self.rank() >= 5,
+        }
+    }
+
+    /// 获取纵坐标（线）
+    pub fn rank(self) -> i32 {
+        (self.0 % 10) as i32
+    }
+
+    /// 转换为 ICCS 坐标，如 `"e0"`
+    pub fn to_iccs(self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Square {
+    /// 以 ICCS 坐标格式输出
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file() as u8) as char, self.rank())
+    }
+}
+
+impl FromStr for Square {
+    type Err = ();
+
+    /// 从 ICCS 坐标解析，如 `"e0"`
+    fn from_str(s: &str) -> Result<Square, ()> {
+        match s.as_bytes() {
+            [file @ b'a'..=b'i', rank @ b'0'..=b'9'] => {
+                Square::new((file - b'a') as i32, (rank - b'0') as i32).ok_or(())
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<Square> for (i32, i32) {
+    /// 转换为坐标
+    fn from(square: Square) -> (i32, i32) {
+        (square.file(), square.rank())
+    }
+}
+
+impl TryFrom<(i32, i32)> for Square {
+    type Error = ();
+
+    /// 从坐标转换，坐标在棋盘外时失败
+    fn try_from(pos: (i32, i32)) -> Result<Square, ()> {
+        Square::new(pos.0, pos.1).ok_or(())
+    }
+}
@@ -4,6 +4,12 @@
  */
 
 use crate::prelude::*;
+use crate::transposition::{Bound, TableEntry, TranspositionTable};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
 
 /// 测试：在被将军时，是否能够选择正确的躲避方式。
 #[test]
@@ -13,9 +19,9 @@ fn human_behavior_1() {
     map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
     map[4][6] = Some(Piece::new(PieceKind::車, Side::Black));
     let board = Board::new_custom(map);
-    let decider = MaxMinDecider::new(SimpleEvaluator::new(), 100000);
-    let decision = decider.make_decision(&board, Side::Red).unwrap();
-    assert!(decision.pos_to == (5, 0));
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 100000);
+    let decision = decider.make_decision(&board, Side::Red).get_move().unwrap();
+    assert!(decision.pos_to == Square::new(5, 0).unwrap());
 }
 
 /// 测试：在己方的帅被威胁时，是否会取吃掉对方的帅。
@@ -27,9 +33,12 @@ fn human_behavior_2() {
     map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
     map[4][6] = Some(Piece::new(PieceKind::車, Side::Black));
     let board = Board::new_custom(map);
-    let decider = MaxMinDecider::new(SimpleEvaluator::new(), 100000);
-    let decision = decider.make_decision(&board, Side::Black).unwrap();
-    assert!(decision.pos_from == (4, 6) && decision.pos_to == (4, 0));
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 100000);
+    let decision = decider
+        .make_decision(&board, Side::Black)
+        .get_move()
+        .unwrap();
+    assert!(decision.pos_from.to_iccs() == "e6" && decision.pos_to.to_iccs() == "e0");
 }
 
 /// 测试：是否会规避循环
@@ -37,28 +46,1267 @@ fn human_behavior_2() {
 fn avoid_loop() {
     let mut board = Board::new();
     board.apply_move_unchecked(&Move::new(
-        (0, 0),
-        (0, 1),
+        Square::new(0, 0).unwrap(),
+        Square::new(0, 1).unwrap(),
         Some(Piece::new(PieceKind::車, Side::Red)),
     ));
     board.apply_move_unchecked(&Move::new(
-        (0, 9),
-        (0, 8),
+        Square::new(0, 9).unwrap(),
+        Square::new(0, 8).unwrap(),
         Some(Piece::new(PieceKind::車, Side::Black)),
     ));
     board.apply_move_unchecked(&Move::new(
-        (0, 1),
-        (0, 0),
+        Square::new(0, 1).unwrap(),
+        Square::new(0, 0).unwrap(),
         Some(Piece::new(PieceKind::車, Side::Red)),
     ));
     board.apply_move_unchecked(&Move::new(
-        (0, 8),
-        (0, 9),
+        Square::new(0, 8).unwrap(),
+        Square::new(0, 9).unwrap(),
         Some(Piece::new(PieceKind::車, Side::Black)),
     ));
     assert!(!board.check_move(&Move::new(
-        (0, 0),
-        (0, 1),
+        Square::new(0, 0).unwrap(),
+        Square::new(0, 1).unwrap(),
         Some(Piece::new(PieceKind::車, Side::Red)),
     )));
 }
+
+/// 测试：静态交换评估是否正确处理炮架的出现与消失
+#[test]
+fn static_exchange_evaluation() {
+    let value = |piece: Piece| match piece.kind {
+        PieceKind::帥 => 2000,
+        PieceKind::車 => 100,
+        PieceKind::馬 => 47,
+        PieceKind::炮 => 53,
+        _ => 20,
+    };
+    let mut map = [[None; 10]; 9];
+    map[5][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[4][1] = Some(Piece::new(PieceKind::炮, Side::Red));
+    map[4][5] = Some(Piece::new(PieceKind::車, Side::Red));
+    map[4][6] = Some(Piece::new(PieceKind::馬, Side::Black));
+    map[4][8] = Some(Piece::new(PieceKind::車, Side::Black));
+    let capture = Move::new(
+        Square::new(4, 5).unwrap(),
+        Square::new(4, 6).unwrap(),
+        Some(Piece::new(PieceKind::車, Side::Red)),
+    );
+    // 車吃馬后被車吃回，炮没有炮架
+    assert_eq!(Board::new_custom(map).see(&capture, value), -53);
+    // 車吃馬，車吃車，炮借卒为架吃回車
+    map[4][3] = Some(Piece::new(PieceKind::濟兵, Side::Black));
+    assert_eq!(Board::new_custom(map).see(&capture, value), 47);
+}
+
+/// 测试：静态交换评估是否计入对将飞吃
+#[test]
+fn static_exchange_evaluation_flying_general() {
+    let value = |piece: Piece| match piece.kind {
+        PieceKind::帥 => 2000,
+        PieceKind::車 => 100,
+        _ => 20,
+    };
+    let mut map = [[None; 10]; 9];
+    map[3][1] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[4][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[4][1] = Some(Piece::new(PieceKind::車, Side::Black));
+    let capture = Move::new(
+        Square::new(3, 1).unwrap(),
+        Square::new(4, 1).unwrap(),
+        Some(Piece::new(PieceKind::帥, Side::Red)),
+    );
+    // 帥吃車后与將对面，被將飞吃
+    assert_eq!(Board::new_custom(map).see(&capture, value), -1900);
+    // 中间有子隔开，帥吃車后安全
+    map[4][3] = Some(Piece::new(PieceKind::中兵, Side::Red));
+    assert_eq!(Board::new_custom(map).see(&capture, value), 100);
+}
+
+/// 测试：位置与 ICCS 坐标的相互转换及范围检查
+#[test]
+fn square_conversion() {
+    let square: Square = "e0".parse().unwrap();
+    assert_eq!((square.file(), square.rank()), (4, 0));
+    assert_eq!(square.to_iccs(), "e0");
+    assert!(square.in_palace(Side::Red) && !square.in_palace(Side::Black));
+    assert!(Square::new(9, 0).is_none() && Square::new(0, -1).is_none());
+    assert!("j0".parse::<Square>().is_err());
+    assert_eq!(Square::all().count(), Square::COUNT);
+    assert!(Square::all().all(|square| Square::from_index(square.index()) == Some(square)));
+}
+
+/// 测试：走子历史、被吃棋子及历史局面
+#[test]
+fn move_history() {
+    let mut board = Board::new();
+    let cannon = Some(Piece::new(PieceKind::炮, Side::Red));
+    for mov in [
+        Move::new("b2".parse().unwrap(), "e2".parse().unwrap(), cannon),
+        Move::new(
+            "b9".parse().unwrap(),
+            "c7".parse().unwrap(),
+            Some(Piece::new(PieceKind::馬, Side::Black)),
+        ),
+        Move::new("e2".parse().unwrap(), "e6".parse().unwrap(), cannon),
+    ] {
+        board.apply_move(&mov).unwrap();
+    }
+    let last = board.get_last_move().unwrap();
+    assert_eq!(last.mov.pos_to.to_iccs(), "e6");
+    assert_eq!(
+        last.piece_captured.map(|piece| piece.kind),
+        Some(PieceKind::中兵)
+    );
+    assert_eq!(board.get_captured_pieces(Side::Black).count(), 1);
+    assert_eq!(board.get_captured_pieces(Side::Red).count(), 0);
+    assert_eq!(board.get_ply_since_capture(), 0);
+    assert_eq!(board.get_move_history().len(), 3);
+    let positions: Vec<Board> = board.get_history_positions().collect();
+    assert_eq!(positions.len(), 4);
+    assert_eq!(positions[0].get_board(), Board::new().get_board());
+    assert_eq!(positions[3].get_board(), board.get_board());
+}
+
+/// 测试：重做及跳转后游戏状态是否正确恢复
+#[test]
+fn redo_and_goto_ply() {
+    let mut map = [[None; 10]; 9];
+    map[4][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[0][0] = Some(Piece::new(PieceKind::車, Side::Red));
+    let mut board = Board::new_custom(map);
+    let chariot = Some(Piece::new(PieceKind::車, Side::Red));
+    board
+        .apply_move(&Move::new(
+            "a0".parse().unwrap(),
+            "d0".parse().unwrap(),
+            chariot,
+        ))
+        .unwrap();
+    board
+        .apply_move(&Move::new(
+            "d9".parse().unwrap(),
+            "e9".parse().unwrap(),
+            Some(Piece::new(PieceKind::帥, Side::Black)),
+        ))
+        .unwrap();
+    board
+        .apply_move(&Move::new(
+            "d0".parse().unwrap(),
+            "d8".parse().unwrap(),
+            chariot,
+        ))
+        .unwrap();
+    board
+        .apply_move(&Move::new(
+            "e9".parse().unwrap(),
+            "e0".parse().unwrap(),
+            Some(Piece::new(PieceKind::帥, Side::Black)),
+        ))
+        .unwrap();
+    assert!(board.game_finished());
+    assert_eq!(board.get_winner(), Some(Side::Black));
+    board.goto_start();
+    assert_eq!(board.get_redo_count(), 4);
+    assert!(!board.game_finished());
+    board.goto_ply(2).unwrap();
+    assert!(board.crossing_occupied("e9".parse().unwrap()));
+    board.goto_end();
+    assert_eq!(board.get_step_count(), 4);
+    assert_eq!(board.get_winner(), Some(Side::Black));
+    assert_eq!(board.goto_ply(5), Err(HistoryError));
+    assert_eq!(board.redo_move(), Err(HistoryError));
+    board.undo_move().unwrap();
+    board.redo_move().unwrap();
+    assert!(board.game_finished());
+}
+
+/// 测试：变着树的添加、提升、删除与遍历
+#[test]
+fn game_tree_variations() {
+    let mut tree = GameTree::new(Board::new(), Side::Red);
+    let root = tree.get_root();
+    let cannon = Some(Piece::new(PieceKind::炮, Side::Red));
+    let main = tree
+        .add_variation(
+            root,
+            &Move::new("b2".parse().unwrap(), "e2".parse().unwrap(), cannon),
+        )
+        .unwrap();
+    let variation = tree
+        .add_variation(
+            root,
+            &Move::new("h2".parse().unwrap(), "e2".parse().unwrap(), cannon),
+        )
+        .unwrap();
+    let reply = tree
+        .add_variation(
+            variation,
+            &Move::new(
+                "h9".parse().unwrap(),
+                "g7".parse().unwrap(),
+                Some(Piece::new(PieceKind::馬, Side::Black)),
+            ),
+        )
+        .unwrap();
+    assert!(tree
+        .add_variation(
+            root,
+            &Move::new("b2".parse().unwrap(), "b5".parse().unwrap(), cannon),
+        )
+        .is_ok());
+    assert!(tree
+        .add_variation(
+            root,
+            &Move::new("b2".parse().unwrap(), "b9".parse().unwrap(), cannon),
+        )
+        .is_ok());
+    assert!(tree
+        .add_variation(
+            root,
+            &Move::new("a0".parse().unwrap(), "a5".parse().unwrap(), None),
+        )
+        .is_err());
+    // 不能连走两步红棋，也不能由黑方先走
+    assert!(tree
+        .add_variation(
+            main,
+            &Move::new("h2".parse().unwrap(), "h5".parse().unwrap(), cannon),
+        )
+        .is_err_and(|error| error == TreeError::IllegalMove));
+    assert!(tree
+        .add_variation(
+            root,
+            &Move::new(
+                "h9".parse().unwrap(),
+                "g7".parse().unwrap(),
+                Some(Piece::new(PieceKind::馬, Side::Black)),
+            ),
+        )
+        .is_err());
+    assert_eq!(tree.get_side_to_move(main), Some(Side::Black));
+    tree.get_node_mut(reply)
+        .unwrap()
+        .comments
+        .push("屏风马".to_string());
+    assert_eq!(tree.get_main_line(), vec![main]);
+    tree.promote_variation(reply).unwrap();
+    assert_eq!(tree.get_main_line(), vec![variation, reply]);
+    assert_eq!(tree.get_board_at(reply).unwrap().get_step_count(), 2);
+    assert_eq!(tree.traverse(root).count(), 6);
+    tree.delete_branch(variation).unwrap();
+    assert!(tree.get_node(reply).is_none());
+    assert_eq!(tree.get_main_line(), vec![main]);
+    // 被删除节点的编号不会被重新使用，仍然查询不到
+    let added = tree
+        .add_variation(
+            main,
+            &Move::new(
+                "h9".parse().unwrap(),
+                "g7".parse().unwrap(),
+                Some(Piece::new(PieceKind::馬, Side::Black)),
+            ),
+        )
+        .unwrap();
+    assert!(added != variation && added != reply);
+    assert!(tree.get_node(variation).is_none());
+    assert!(tree.get_node(reply).is_none());
+    assert_eq!(tree.traverse(root).count(), 5);
+    assert_eq!(tree.delete_branch(root), Err(TreeError::RootNode));
+    assert_eq!(tree.delete_branch(reply), Err(TreeError::NodeNotFound));
+    assert_eq!(tree.promote_variation(reply), Err(TreeError::NodeNotFound));
+}
+
+/// 测试：主要变例中的每一步都合法，且双方轮流走子
+#[test]
+fn principal_variation_is_legal() {
+    let mut board = Board::new();
+    let decider = MaxMinDecider::new(SimpleEvaluator::new(), 20000);
+    let pv = decider.principal_variation(&board, Side::Red);
+    assert!(pv.len() >= 2);
+    for (index, mov) in pv.iter().enumerate() {
+        let side = if index % 2 == 0 {
+            Side::Red
+        } else {
+            Side::Black
+        };
+        assert_eq!(mov.turn_into.map(|piece| piece.side), Some(side));
+        board.apply_move(mov).unwrap();
+    }
+}
+
+/// 测试：局面哈希随走子和悔棋正确更新，置换表能够存取条目
+#[test]
+fn transposition_table_round_trip() {
+    let mut board = Board::new();
+    let start_hash = board.get_hash();
+    let mov = Move::new(
+        "h2".parse().unwrap(),
+        "h9".parse().unwrap(),
+        Some(Piece::new(PieceKind::炮, Side::Red)),
+    );
+    board.apply_move(&mov).unwrap();
+    assert_ne!(board.get_hash(), start_hash);
+    let mut map = *board.get_board();
+    assert_eq!(Board::new_custom(map).get_hash(), board.get_hash());
+    board.undo_move().unwrap();
+    assert_eq!(board.get_hash(), start_hash);
+    map[0][0] = None;
+    assert_ne!(Board::new_custom(map).get_hash(), start_hash);
+
+    let table = TranspositionTable::new(1);
+    let key = TranspositionTable::key(start_hash, Side::Red);
+    let entry = TableEntry {
+        depth: 3,
+        bound: Bound::Lower,
+        score: Score::mated_in(5),
+        best_move: Some(mov),
+    };
+    table.store(key, entry);
+    assert_eq!(table.probe(key), Some(entry));
+    assert_eq!(
+        table.probe(TranspositionTable::key(start_hash, Side::Black)),
+        None
+    );
+    table.clear();
+    assert_eq!(table.probe(key), None);
+
+    // 同一槽位上，本次搜索的深条目不被浅条目替换，之前搜索的条目总被替换
+    let other = key.wrapping_add(table.len() as u64);
+    let shallow = TableEntry { depth: 1, ..entry };
+    table.store(key, entry);
+    table.store(other, shallow);
+    assert_eq!(table.probe(key), Some(entry));
+    table.new_search();
+    table.new_search();
+    table.store(other, shallow);
+    assert_eq!(table.probe(key), None);
+    assert_eq!(table.probe(other), Some(shallow));
+}
+
+/// 测试：搜索深度很浅时，是否仍能看到吃子之后的反吃
+#[test]
+fn quiescence_avoids_horizon_effect() {
+    let mut map = [[None; 10]; 9];
+    map[3][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[5][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[0][4] = Some(Piece::new(PieceKind::車, Side::Red));
+    map[0][7] = Some(Piece::new(PieceKind::馬, Side::Black));
+    map[0][9] = Some(Piece::new(PieceKind::車, Side::Black));
+    let board = Board::new_custom(map);
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 40);
+    let decision = decider.make_decision(&board, Side::Red).get_move().unwrap();
+    assert!(decision.pos_to.to_iccs() != "a7");
+}
+
+/// 测试：限时搜索是否按时返回
+#[test]
+fn search_respects_move_time() {
+    let board = Board::new();
+    let mut decider = MaxMinDecider::with_limits(
+        SimpleEvaluator::new(),
+        SearchLimits::move_time(Duration::from_millis(300)),
+    );
+    let start = Instant::now();
+    let decision = decider.make_decision(&board, Side::Red).get_move().unwrap();
+    assert!(start.elapsed() < Duration::from_millis(1000));
+    assert!(board.check_move(&decision));
+}
+
+/// 测试：无限搜索能否被停止信号中断，并返回合法走法
+#[test]
+fn search_stops_on_signal() {
+    let board = Board::new();
+    let mut decider = MaxMinDecider::with_limits(SimpleEvaluator::new(), SearchLimits::infinite());
+    let signal = StopSignal::from(Arc::new(AtomicBool::new(false)));
+    decider.set_stop_signal(signal.clone());
+    let start = Instant::now();
+    let stopper = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        signal.stop();
+    });
+    let decision = decider.make_decision(&board, Side::Red).get_move().unwrap();
+    stopper.join().unwrap();
+    assert!(start.elapsed() < Duration::from_millis(1000));
+    assert!(board.check_move(&decision));
+    // 信号不会自动复位，之后的搜索立即停止，即使信号在搜索开始前发出
+    assert!(decider.get_stop_signal().is_stopped());
+    let start = Instant::now();
+    let decision = decider.make_decision(&board, Side::Red).get_move().unwrap();
+    assert!(start.elapsed() < Duration::from_millis(1000));
+    assert!(board.check_move(&decision));
+    assert!(decider.get_stop_signal().is_stopped());
+    // 持有者复位后不再影响之后的搜索
+    decider.get_stop_signal().reset();
+    decider.set_limits(SearchLimits::depth(3));
+    let result = decider.search(&board, Side::Red);
+    assert_eq!(result.depth, 3);
+    // 一步都没有搜完时结果中没有走法，但作出决定时仍然走一步合法的
+    decider.set_limits(SearchLimits::nodes(1));
+    let result = decider.search(&board, Side::Red);
+    assert_eq!((result.best_move, result.depth), (None, 0));
+    let decision = decider.make_decision(&board, Side::Red).get_move().unwrap();
+    assert!(board.check_move(&decision));
+}
+
+/// 测试：因节点数限制中断时，结果中的深度为最后完成的一轮迭代
+#[test]
+fn interrupted_search_reports_completed_depth() {
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 5000);
+    let depths = Arc::new(Mutex::new(Vec::new()));
+    let completed = depths.clone();
+    decider.set_progress_callback(ProgressCallback::new(move |result| {
+        completed.lock().unwrap().push(result.depth);
+    }));
+    let result = decider.search(&Board::new(), Side::Red);
+    assert!(result.best_move.is_some());
+    assert_eq!(result.node_count, 5001);
+    assert_eq!(Some(&result.depth), depths.lock().unwrap().last());
+}
+
+/// 测试：静态搜索中被将军的一方不能停着，没有将军延伸时一层搜索也能发现将死
+#[test]
+fn quiescence_evades_check() {
+    let mut map = [[None; 10]; 9];
+    map[4][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[0][8] = Some(Piece::new(PieceKind::車, Side::Red));
+    map[8][7] = Some(Piece::new(PieceKind::車, Side::Red));
+    let board = Board::new_custom(map);
+    let mut decider = MaxMinDecider::with_limits(SimpleEvaluator::new(), SearchLimits::depth(1));
+    decider.set_config(SearchConfig::none());
+    let result = decider.search(&board, Side::Red);
+    // 将军、对方应将、吃掉帥 / 將，按回合数计为两步
+    assert_eq!(result.score, SearchScore::Mate(2));
+    assert_eq!(result.best_move.unwrap().pos_to, Square::new(8, 9).unwrap());
+}
+
+/// 测试：搜索结果中的分数、深度和主要变例，以及每轮迭代的进度回调
+#[test]
+fn search_result_reports_mate() {
+    let mut map = [[None; 10]; 9];
+    map[4][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[0][8] = Some(Piece::new(PieceKind::車, Side::Red));
+    map[8][7] = Some(Piece::new(PieceKind::車, Side::Red));
+    let board = Board::new_custom(map);
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 200000);
+    let iterations = Arc::new(AtomicUsize::new(0));
+    let counter = iterations.clone();
+    decider.set_progress_callback(ProgressCallback::new(move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }));
+    let result = decider.search(&board, Side::Red);
+    assert!(matches!(result.score, SearchScore::Mate(n) if n > 0));
+    assert_eq!(
+        result.best_move,
+        result.principal_variation.first().copied()
+    );
+    assert_eq!(
+        result.ponder_move,
+        result.principal_variation.get(1).copied()
+    );
+    assert!(result.node_count > 0);
+    assert_eq!(iterations.load(Ordering::Relaxed), result.depth as usize);
+}
+
+/// 测试：多变例分析返回互不相同的候选走法，并按分数从高到低排列
+#[test]
+fn multi_pv_returns_distinct_lines() {
+    let board = Board::new();
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 200000);
+    decider.set_limits(SearchLimits::depth(2));
+    let lines = decider.search_multi_pv(&board, Side::Red, 3);
+    assert_eq!(lines.len(), 3);
+    let mut previous = i32::MAX;
+    for (index, line) in lines.iter().enumerate() {
+        let mov = line.best_move.unwrap();
+        assert!(board.check_move(&mov));
+        assert!(lines[..index]
+            .iter()
+            .all(|other| other.best_move != Some(mov)));
+        let SearchScore::Evaluation(score) = line.score else {
+            panic!("开局不应算出杀棋");
+        };
+        assert!(score <= previous);
+        previous = score;
+    }
+}
+
+/// 测试：走法排序使大多数截断发生在第一个走法上
+#[test]
+fn move_ordering_cuts_off_early() {
+    let board = Board::new();
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 200000);
+    decider.set_limits(SearchLimits::depth(4));
+    let result = decider.search(&board, Side::Red);
+    assert!(result.cutoff_count > 0);
+    assert!(result.first_move_cutoff_count <= result.cutoff_count);
+    assert!(result.get_first_move_cutoff_rate() > 0.5);
+}
+
+/// 测试：选择性搜索减少搜索的节点数，且各项技术可以单独关闭
+#[test]
+fn selective_search_reduces_nodes() {
+    let board = Board::new();
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 1000000);
+    decider.set_limits(SearchLimits::depth(4));
+    decider.set_config(SearchConfig::none());
+    let full = decider.search(&board, Side::Red);
+    decider.clear_transposition_table();
+    decider.set_config(SearchConfig::default());
+    let selective = decider.search(&board, Side::Red);
+    assert_eq!(full.depth, 4);
+    assert_eq!(selective.depth, 4);
+    assert!(selective.node_count < full.node_count);
+    assert!(board.check_move(&selective.best_move.unwrap()));
+}
+
+/// 测试：多线程搜索完成指定深度并得到合法走法
+#[test]
+fn parallel_search() {
+    let board = Board::new();
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 1000000);
+    decider.set_limits(SearchLimits::depth(3));
+    decider.set_thread_count(4);
+    assert_eq!(decider.get_thread_count(), 4);
+    let result = decider.search(&board, Side::Red);
+    assert_eq!(result.depth, 3);
+    assert!(board.check_move(&result.best_move.unwrap()));
+}
+
+/// 测试：未设置种子时，单线程搜索的结果可以重现
+#[test]
+fn single_thread_search_is_deterministic() {
+    let board = Board::new();
+    let search = || MaxMinDecider::new(SimpleEvaluator::new(), 3000).search(&board, Side::Red);
+    let first = search();
+    let second = search();
+    assert!(first.best_move.is_some());
+    assert_eq!(first.best_move, second.best_move);
+    assert_eq!(first.score, second.score);
+    assert_eq!(first.node_count, second.node_count);
+}
+
+/// 测试：整数分数的杀棋范围、回合数和置换表转换
+#[test]
+fn score_mate_range() {
+    assert_eq!(Score::new(i32::MAX), Score::MAX_EVALUATION);
+    assert!(!Score::MAX_EVALUATION.is_mate());
+    assert!(Score::mate_in(1).is_mate());
+    assert!(Score::mate_in(3) > Score::mate_in(5));
+    assert!(Score::mated_in(2) < -Score::MAX_EVALUATION);
+    assert_eq!(Score::mate_in(3).get_mate_moves(), Some(2));
+    assert_eq!(Score::mated_in(4).get_mate_moves(), Some(-2));
+    assert_eq!(Score::new(120).get_mate_moves(), None);
+    assert_eq!(SearchScore::from(Score::mate_in(1)), SearchScore::Mate(1));
+    assert_eq!(
+        SearchScore::from(Score::new(-35)),
+        SearchScore::Evaluation(-35)
+    );
+    assert_eq!(Score::mate_in(7).to_table(4), Score::mate_in(3));
+    assert_eq!(Score::mate_in(3).from_table(4), Score::mate_in(7));
+    assert_eq!(
+        Score::mated_in(6).to_table(2).from_table(2),
+        Score::mated_in(6)
+    );
+    assert_eq!(Score::new(50).to_table(9), Score::new(50));
+}
+
+/// 测试：蒙特卡洛树搜索按迭代次数搜索，能抓住吃将的机会，并可复用搜索树
+#[test]
+fn mcts_decider() {
+    let mut map = [[None; 10]; 9];
+    map[4][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[3][5] = Some(Piece::new(PieceKind::車, Side::Red));
+    map[0][9] = Some(Piece::new(PieceKind::車, Side::Black));
+    let board = Board::new_custom(map);
+    for selection in [SelectionPolicy::Uct(1.4), SelectionPolicy::Puct(1.4)] {
+        let mut decider = MctsDecider::new(SimpleEvaluator::new(), 500);
+        decider.set_config(MctsConfig {
+            selection,
+            ..MctsConfig::default()
+        });
+        let result = decider.search(&board, Side::Red);
+        assert_eq!(result.node_count, 500);
+        let mov = result.best_move.unwrap();
+        assert_eq!(mov.pos_from, Square::new(3, 5).unwrap());
+        assert_eq!(mov.pos_to, Square::new(3, 9).unwrap());
+        let result = decider.search(&board, Side::Red);
+        assert_eq!(result.node_count, 500);
+        assert!(board.check_move(&result.best_move.unwrap()));
+    }
+}
+
+/// 测试：设置同样的总种子后，整盘对局可以完全重现
+#[test]
+fn seeded_game_replays() {
+    let play = |seed: u64| {
+        let mut game = Game::new(
+            RandomDecider::new(),
+            MaxMinDecider::new(SimpleEvaluator::new(), 2000),
+        );
+        game.set_seed(seed);
+        let mut board = Board::new();
+        let winner = game.go(&mut board).winner;
+        (
+            winner,
+            board
+                .get_move_history()
+                .iter()
+                .map(|record| record.mov)
+                .collect::<Vec<Move>>(),
+        )
+    };
+    let (winner, history) = play(42);
+    assert!(!history.is_empty());
+    assert_eq!(play(42), (winner, history));
+}
+
+/// 测试：未设置种子时各决定器使用同一个固定的默认种子，新构造的决定器走法相同
+#[test]
+fn default_seed_replays() {
+    let play = || {
+        let mut deciders: Vec<Box<dyn Decider>> = vec![
+            Box::new(RandomDecider::new()),
+            Box::new(MctsDecider::new(SimpleEvaluator::new(), 200)),
+            Box::new(SkillDecider::new(SimpleEvaluator::new(), 1)),
+        ];
+        let mut board = Board::new();
+        let mut history = Vec::new();
+        for ply in 0..6 {
+            let side = if ply % 2 == 0 { Side::Red } else { Side::Black };
+            let mov = deciders[ply % 3]
+                .make_decision(&board, side)
+                .get_move()
+                .unwrap();
+            board.apply_move(&mov).unwrap();
+            history.push(mov);
+        }
+        history
+    };
+    assert_eq!(play(), play());
+}
+
+/// 记录生命周期事件的决定器，走子交给随机决定器
+struct RecordingDecider {
+    inner: RandomDecider,
+    events: Arc<Mutex<Vec<String>>>,
+    moves: usize,
+}
+
+impl Decider for RecordingDecider {
+    /// 记录结果和己方走子数
+    fn game_over(&mut self, outcome: &GameOutcome) {
+        let event = format!("game_over {:?} after {}", outcome.reason, self.moves);
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// 计数并随机走子
+    fn make_decision(&mut self, board: &Board, side: Side) -> Decision {
+        self.moves += 1;
+        self.inner.make_decision(board, side)
+    }
+
+    /// 记录执的一方
+    fn new_game(&mut self, _board: &Board, side: Side) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("new_game {:?}", side));
+    }
+
+    /// 检查通知的走子即局面的最后一步
+    fn opponent_moved(&mut self, board: &Board, mov: &Move) {
+        assert_eq!(board.get_last_move().unwrap().mov, *mov);
+        self.events
+            .lock()
+            .unwrap()
+            .push("opponent_moved".to_string());
+    }
+}
+
+/// 测试：游戏在开始、对方走子和结束时调用决定器的生命周期钩子
+#[test]
+fn decider_lifecycle_hooks() {
+    let red_events = Arc::new(Mutex::new(Vec::new()));
+    let black_events = Arc::new(Mutex::new(Vec::new()));
+    let mut game = Game::new(
+        RecordingDecider {
+            inner: RandomDecider::new(),
+            events: red_events.clone(),
+            moves: 0,
+        },
+        RecordingDecider {
+            inner: RandomDecider::new(),
+            events: black_events.clone(),
+            moves: 0,
+        },
+    );
+    game.set_seed(7);
+    let mut board = Board::new();
+    let outcome = game.go(&mut board);
+    let plies = outcome.move_count as usize;
+    let red_events = red_events.lock().unwrap();
+    let black_events = black_events.lock().unwrap();
+    assert_eq!(red_events.first().unwrap(), "new_game Red");
+    assert_eq!(black_events.first().unwrap(), "new_game Black");
+    let red_moves = plies.div_ceil(2);
+    let black_moves = plies / 2;
+    assert_eq!(
+        red_events.last().unwrap(),
+        &format!("game_over {:?} after {}", outcome.reason, red_moves)
+    );
+    assert_eq!(
+        black_events.last().unwrap(),
+        &format!("game_over {:?} after {}", outcome.reason, black_moves)
+    );
+    // 走子使棋盘判定终局时直接结束，不再通知对方
+    let notified = |events: &[String]| {
+        events
+            .iter()
+            .filter(|event| *event == "opponent_moved")
+            .count()
+    };
+    let board_finished = matches!(
+        outcome.reason,
+        TerminationReason::GeneralCaptured | TerminationReason::StepLimit
+    );
+    let red_last = plies % 2 == 1;
+    assert_eq!(
+        notified(&red_events),
+        black_moves - usize::from(board_finished && !red_last)
+    );
+    assert_eq!(
+        notified(&black_events),
+        red_moves - usize::from(board_finished && red_last)
+    );
+}
+
+/// 测试：后台思考，猜中时继续搜索到深度、节点数或时间限制并采用其结果，没有猜中时重新搜索
+#[test]
+fn pondering() {
+    let iterations = Arc::new(AtomicUsize::new(0));
+    let counter = iterations.clone();
+    let mut decider = MaxMinDecider::with_limits(SimpleEvaluator::new(), SearchLimits::infinite());
+    // 后台思考不调用进度回调，由此区分是否采用了后台思考的结果
+    decider.set_progress_callback(ProgressCallback::new(move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }));
+    decider.set_pondering(true);
+    let mut board = Board::new();
+    for limits in [
+        SearchLimits::depth(4),
+        SearchLimits::nodes(20000),
+        SearchLimits::move_time(Duration::from_millis(200)),
+    ] {
+        decider.set_limits(limits);
+        iterations.store(0, Ordering::Relaxed);
+        let result = decider.search(&board, Side::Red);
+        assert!(board.check_move(&result.best_move.unwrap()));
+        assert!(iterations.load(Ordering::Relaxed) > 0);
+        assert!(decider.is_pondering());
+
+        // 猜中
+        board.apply_move(&result.best_move.unwrap()).unwrap();
+        let ponder_move = result.ponder_move.unwrap();
+        board.apply_move(&ponder_move).unwrap();
+        decider.opponent_moved(&board, &ponder_move);
+        assert!(decider.is_pondering());
+        iterations.store(0, Ordering::Relaxed);
+        let reply = decider.search(&board, Side::Red);
+        assert!(board.check_move(&reply.best_move.unwrap()));
+        assert_eq!(iterations.load(Ordering::Relaxed), 0);
+        assert!(reply.depth >= 1);
+        if let Some(depth) = limits.depth {
+            assert_eq!(reply.depth, depth);
+        }
+        if let Some(nodes) = limits.nodes {
+            // 达到节点数限制才停止，中断搜索的节点也计入
+            assert_eq!(reply.node_count, nodes + 1);
+        }
+
+        // 没有猜中
+        board.apply_move(&reply.best_move.unwrap()).unwrap();
+        let other = board
+            .query_possible_moves_of_side(Side::Black)
+            .find(|step| Some(*step) != reply.ponder_move)
+            .unwrap();
+        board.apply_move(&other).unwrap();
+        decider.opponent_moved(&board, &other);
+        assert!(!decider.is_pondering());
+    }
+    decider.set_pondering(false);
+    assert!(!decider.is_pondering());
+}
+
+/// 测试：在后台线程中进行游戏，双方后台思考
+#[test]
+fn spawned_game() {
+    let mut game = Game::new(
+        MaxMinDecider::with_limits(SimpleEvaluator::new(), SearchLimits::depth(2)),
+        MaxMinDecider::with_limits(SimpleEvaluator::new(), SearchLimits::depth(2)),
+    );
+    game.set_pondering(true);
+    let handle = game.spawn(Board::new());
+    let (mut game, outcome) = handle.join();
+    assert_eq!(outcome.board.get_move_count(), outcome.move_count);
+    assert!(outcome.move_count > 0);
+    // 取回的游戏可以继续使用
+    let again = game.go(&mut Board::new());
+    assert!(again.move_count > 0);
+}
+
+/// 测试：棋力等级的范围和搜索限制，最高等级总是选择最佳走法
+#[test]
+fn skill_levels() {
+    assert_eq!(SkillDecider::new(SimpleEvaluator::new(), 0).get_level(), 1);
+    assert_eq!(
+        SkillDecider::new(SimpleEvaluator::new(), 99).get_level(),
+        20
+    );
+    let mut previous = SearchLimits::default();
+    for level in 1..=20 {
+        let limits = SkillDecider::<SimpleEvaluator>::level_limits(level);
+        assert!(limits.depth >= previous.depth);
+        assert!(limits.nodes > previous.nodes);
+        previous = limits;
+    }
+    let mut map = [[None; 10]; 9];
+    map[4][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[3][5] = Some(Piece::new(PieceKind::車, Side::Red));
+    map[0][9] = Some(Piece::new(PieceKind::車, Side::Black));
+    let board = Board::new_custom(map);
+    let mut decider = SkillDecider::new(SimpleEvaluator::new(), 20);
+    for seed in 0..5 {
+        decider.set_seed(seed);
+        let mov = decider.make_decision(&board, Side::Red).get_move().unwrap();
+        assert_eq!(mov.pos_to, Square::new(3, 9).unwrap());
+    }
+}
+
+/// 棋力校准：相隔四级的决定器自我对局十盘，高等级应当至少胜七盘
+///
+/// 耗时较长，用 `cargo test --release -- --ignored skill_calibration` 运行，
+/// 调整 skill 模块中的常量后应重新运行，失败信息中给出胜、和、负盘数。
+#[test]
+#[ignore]
+fn skill_calibration() {
+    for (low, high) in [(1, 4), (4, 8), (8, 12), (12, 16), (16, 20)] {
+        let (wins, draws, losses) = skill_match(low, high, 10, 0);
+        assert!(
+            wins >= 7,
+            "等级 {} 对 {}：胜 {} 和 {} 负 {}",
+            high,
+            low,
+            wins,
+            draws,
+            losses
+        );
+    }
+}
+
+/// 两个等级的棋力决定器对局若干盘，轮流执红，每盘的种子依次递增，返回高等级的胜、和、负盘数
+fn skill_match(low: u8, high: u8, games: u64, seed: u64) -> (u32, u32, u32) {
+    let mut ret = (0, 0, 0);
+    for index in 0..games {
+        let (high_side, red, black) = match index % 2 {
+            0 => (Side::Red, high, low),
+            _ => (Side::Black, low, high),
+        };
+        let mut game = Game::new(
+            SkillDecider::new(SimpleEvaluator::new(), red),
+            SkillDecider::new(SimpleEvaluator::new(), black),
+        );
+        game.set_seed(seed + index);
+        match game.go(&mut Board::new()).winner {
+            Some(side) if side == high_side => ret.0 += 1,
+            Some(_) => ret.2 += 1,
+            None => ret.1 += 1,
+        }
+    }
+    ret
+}
+
+/// 按脚本作出决定的决定器，脚本的第三个参数为对方是否刚刚提出和棋
+struct ScriptedDecider<F: FnMut(&Board, Side, bool) -> Decision> {
+    script: F,
+    offered: bool,
+}
+
+impl<F: FnMut(&Board, Side, bool) -> Decision> ScriptedDecider<F> {
+    /// 以脚本构造
+    fn new(script: F) -> ScriptedDecider<F> {
+        ScriptedDecider {
+            script,
+            offered: false,
+        }
+    }
+}
+
+impl<F: FnMut(&Board, Side, bool) -> Decision> Decider for ScriptedDecider<F> {
+    /// 记下对方提出和棋
+    fn draw_offered(&mut self, _board: &Board) {
+        self.offered = true;
+    }
+
+    /// 按脚本作出决定
+    fn make_decision(&mut self, board: &Board, side: Side) -> Decision {
+        let offered = std::mem::take(&mut self.offered);
+        (self.script)(board, side, offered)
+    }
+}
+
+/// 测试：认输、提出和接受和棋、不合法的和棋要求
+#[test]
+fn draw_offer_and_resignation() {
+    let first_move = |board: &Board, side| board.query_possible_moves_of_side(side).next();
+    // 红方走子并求和，黑方在收到求和后接受
+    let mut game = Game::new(
+        ScriptedDecider::new(move |board: &Board, side, _| {
+            Decision::OfferDraw(first_move(board, side).unwrap())
+        }),
+        ScriptedDecider::new(move |board: &Board, side, offered| {
+            if offered {
+                Decision::AcceptDraw
+            } else {
+                Decision::from(first_move(board, side))
+            }
+        }),
+    );
+    let outcome = game.go(&mut Board::new());
+    assert_eq!(outcome.winner, None);
+    assert_eq!(outcome.reason, TerminationReason::Agreement);
+    assert_eq!(outcome.move_count, 1);
+    // 红方走子，黑方认输
+    let mut game = Game::new(
+        ScriptedDecider::new(move |board: &Board, side, _| Decision::from(first_move(board, side))),
+        ScriptedDecider::new(|_: &Board, _, _| Decision::Resign),
+    );
+    let outcome = game.go(&mut Board::new());
+    assert_eq!(outcome.winner, Some(Side::Red));
+    assert_eq!(outcome.reason, TerminationReason::Resignation);
+    // 没有求和时接受和棋、未到自然限着时要求判和都不合法
+    let mut game = Game::new(
+        ScriptedDecider::new(|_: &Board, _, _| Decision::AcceptDraw),
+        ScriptedDecider::new(|_: &Board, _, _| Decision::Resign),
+    );
+    assert_eq!(
+        game.go(&mut Board::new()).reason,
+        TerminationReason::IllegalMove(Side::Red)
+    );
+    let mut game = Game::new(
+        ScriptedDecider::new(move |board: &Board, side, _| Decision::from(first_move(board, side))),
+        ScriptedDecider::new(|_: &Board, _, _| Decision::ClaimDraw),
+    );
+    assert_eq!(
+        game.go(&mut Board::new()).reason,
+        TerminationReason::IllegalMove(Side::Black)
+    );
+}
+
+/// 测试：将死、困毙、循环不变着和超时的对局结果
+#[test]
+fn game_outcome_reasons() {
+    let first_move = |board: &Board, side| board.query_possible_moves_of_side(side).next();
+    // 红帅被黑车将死
+    let mut map = [[None; 10]; 9];
+    map[4][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[3][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[4][6] = Some(Piece::new(PieceKind::車, Side::Black));
+    map[3][6] = Some(Piece::new(PieceKind::車, Side::Black));
+    map[5][6] = Some(Piece::new(PieceKind::車, Side::Black));
+    let mut game = Game::new(RandomDecider::new(), RandomDecider::new());
+    let outcome = game.go(&mut Board::new_custom(map));
+    assert_eq!(outcome.winner, Some(Side::Black));
+    assert_eq!(outcome.reason, TerminationReason::Checkmate);
+    assert_eq!(outcome.move_count, 0);
+    // 红帅没有被将军，但无论怎样走都会被吃
+    let mut map = [[None; 10]; 9];
+    map[3][0] = Some(Piece::new(PieceKind::帥, Side::Red));
+    map[5][9] = Some(Piece::new(PieceKind::帥, Side::Black));
+    map[4][5] = Some(Piece::new(PieceKind::車, Side::Black));
+    map[0][1] = Some(Piece::new(PieceKind::車, Side::Black));
+    let outcome = game.go(&mut Board::new_custom(map));
+    assert_eq!(outcome.winner, Some(Side::Black));
+    assert_eq!(outcome.reason, TerminationReason::Stalemate);
+    // 双方车来回走，红方第五步不变着
+    let shuttle = |file: i32, home: i32, away: i32| {
+        move |board: &Board, side, _| {
+            let home = Square::new(file, home).unwrap();
+            let away = Square::new(file, away).unwrap();
+            let piece = Piece::new(PieceKind::車, side);
+            if board.get_piece_at(home) == Some(piece) {
+                Decision::Move(Move::new(home, away, Some(piece)))
+            } else {
+                Decision::Move(Move::new(away, home, Some(piece)))
+            }
+        }
+    };
+    let mut game = Game::new(
+        ScriptedDecider::new(shuttle(0, 0, 1)),
+        ScriptedDecider::new(shuttle(0, 9, 8)),
+    );
+    let outcome = game.go(&mut Board::new());
+    assert_eq!(outcome.winner, Some(Side::Black));
+    assert_eq!(outcome.reason, TerminationReason::Repetition(Side::Red));
+    assert_eq!(outcome.move_count, 4);
+    // 红方用完剩余时间
+    let mut game = Game::new(
+        ScriptedDecider::new(move |board: &Board, side, _| {
+            std::thread::sleep(Duration::from_millis(20));
+            Decision::from(first_move(board, side))
+        }),
+        RandomDecider::new(),
+    );
+    game.set_clock(Some(Clock {
+        remaining: Duration::from_millis(10),
+        increment: Duration::ZERO,
+        moves_to_go: None,
+    }));
+    let outcome = game.go(&mut Board::new());
+    assert_eq!(outcome.winner, Some(Side::Black));
+    assert_eq!(outcome.reason, TerminationReason::Timeout(Side::Red));
+}
+
+/// 测试：设置棋钟不影响决定器原有的节点数限制
+#[test]
+fn clock_keeps_limits() {
+    let clock = Clock {
+        remaining: Duration::from_secs(600),
+        increment: Duration::ZERO,
+        moves_to_go: None,
+    };
+    let mut decider = MaxMinDecider::new(SimpleEvaluator::new(), 500);
+    decider.set_clock(Some(clock));
+    assert_eq!(decider.get_limits().nodes, Some(500));
+    assert!(decider.get_limits().clock.is_some());
+    let result = decider.search(&Board::new(), Side::Red);
+    assert!(result.node_count <= 501);
+    decider.set_clock(None);
+    assert!(decider.get_limits().clock.is_none());
+    // 对局中每次决定前设置棋钟，红方的每次搜索仍然不超过节点数限制
+    let mut game = Game::new(
+        MaxMinDecider::new(SimpleEvaluator::new(), 500),
+        RandomDecider::new(),
+    );
+    game.set_clock(Some(clock));
+    game.set_seed(0);
+    let node_counts = Arc::new(Mutex::new(Vec::new()));
+    game.add_observer(NodeCountObserver {
+        node_counts: node_counts.clone(),
+    });
+    game.go(&mut Board::new());
+    let node_counts = node_counts.lock().unwrap();
+    assert!(!node_counts.is_empty());
+    assert!(node_counts.iter().all(|&count| count <= 501));
+}
+
+/// 记录红方每轮迭代加深的节点数的观察者
+struct NodeCountObserver {
+    node_counts: Arc<Mutex<Vec<u64>>>,
+}
+
+impl GameObserver for NodeCountObserver {
+    /// 记录红方的节点数
+    fn on_search_info(&mut self, side: Side, result: &SearchResult) {
+        if side == Side::Red {
+            self.node_counts.lock().unwrap().push(result.node_count);
+        }
+    }
+}
+
+/// 测试：不合法的走子判负并记录，设置重试次数后可以重新决定
+#[test]
+fn illegal_move_forfeits() {
+    let first_move = |board: &Board, side| board.query_possible_moves_of_side(side).next();
+    let illegal = Move::new(
+        Square::new(0, 0).unwrap(),
+        Square::new(5, 5).unwrap(),
+        Some(Piece::new(PieceKind::車, Side::Red)),
+    );
+    // 红方第一次走子不合法，之后正常走子，黑方随后认输
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let red_attempts = attempts.clone();
+    let mut game = Game::new(
+        ScriptedDecider::new(move |board: &Board, side, _| {
+            if red_attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                Decision::Move(illegal)
+            } else {
+                Decision::from(first_move(board, side))
+            }
+        }),
+        ScriptedDecider::new(|_: &Board, _, _| Decision::Resign),
+    );
+    let outcome = game.go(&mut Board::new());
+    assert_eq!(outcome.winner, Some(Side::Black));
+    assert_eq!(outcome.reason, TerminationReason::IllegalMove(Side::Red));
+    assert_eq!(outcome.illegal_decision, Some(Decision::Move(illegal)));
+    assert_eq!(outcome.move_count, 0);
+    attempts.store(0, Ordering::SeqCst);
+    game.set_illegal_move_retries(1);
+    let outcome = game.go(&mut Board::new());
+    assert_eq!(outcome.winner, Some(Side::Red));
+    assert_eq!(outcome.reason, TerminationReason::Resignation);
+    assert_eq!(outcome.illegal_decision, None);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    // 红方走黑方的棋子
+    let black_move = first_move(&Board::new(), Side::Black).unwrap();
+    let mut game = Game::new(
+        ScriptedDecider::new(move |_: &Board, _, _| Decision::Move(black_move)),
+        ScriptedDecider::new(|_: &Board, _, _| Decision::Resign),
+    );
+    let outcome = game.go(&mut Board::new());
+    assert_eq!(outcome.winner, Some(Side::Black));
+    assert_eq!(outcome.reason, TerminationReason::IllegalMove(Side::Red));
+    assert_eq!(outcome.illegal_decision, Some(Decision::Move(black_move)));
+    assert_eq!(outcome.move_count, 0);
+}
+
+/// 记录对局事件的观察者
+struct RecordingObserver {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl GameObserver for RecordingObserver {
+    /// 记录被将军的一方
+    fn on_check(&mut self, _board: &Board, side: Side) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("check {:?}", side));
+    }
+
+    /// 记录结果
+    fn on_game_end(&mut self, outcome: &GameOutcome) {
+        let event = format!("end {:?}", outcome.reason);
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// 记录开始
+    fn on_game_start(&mut self, _board: &Board) {
+        self.events.lock().unwrap().push("start".to_string());
+    }
+
+    /// 检查通知的走子即局面的最后一步，并记录走子方
+    fn on_move(&mut self, board: &Board, mov: &Move, side: Side, _elapsed: Duration) {
+        assert_eq!(board.get_last_move().unwrap().mov, *mov);
+        self.events.lock().unwrap().push(format!("move {:?}", side));
+    }
+
+    /// 记录搜索信息来自哪一方
+    fn on_search_info(&mut self, side: Side, _result: &SearchResult) {
+        self.events.lock().unwrap().push(format!("info {:?}", side));
+    }
+}
+
+/// 测试：多个观察者都收到包括最后一步在内的所有对局事件
+#[test]
+fn game_observers() {
+    let mut game = Game::new(
+        RandomDecider::new(),
+        MaxMinDecider::new(SimpleEvaluator::new(), 2000),
+    );
+    game.set_seed(3);
+    let first = Arc::new(Mutex::new(Vec::new()));
+    let second = Arc::new(Mutex::new(Vec::new()));
+    game.add_observer(RecordingObserver {
+        events: first.clone(),
+    });
+    game.add_observer(RecordingObserver {
+        events: second.clone(),
+    });
+    let mut board = Board::new();
+    let outcome = game.go(&mut board);
+    let events = first.lock().unwrap();
+    assert_eq!(*events, *second.lock().unwrap());
+    assert_eq!(events.first().unwrap(), "start");
+    assert_eq!(events.last().unwrap(), &format!("end {:?}", outcome.reason));
+    let count = |event: &str| events.iter().filter(|e| *e == event).count();
+    let plies = outcome.move_count as usize;
+    assert_eq!(count("move Red"), plies.div_ceil(2));
+    assert_eq!(count("move Black"), plies / 2);
+    assert_eq!(count("info Red"), 0);
+    assert!(count("info Black") > 0);
+    // 被将军的次数与历史局面中走子方被将军的次数相同
+    let checks = board
+        .get_history_positions()
+        .enumerate()
+        .skip(1)
+        .filter(|(ply, position)| {
+            let side = if ply % 2 == 0 { Side::Red } else { Side::Black };
+            !position.game_finished() && position.general_threatened(side)
+        })
+        .count();
+    assert_eq!(count("check Red") + count("check Black"), checks);
+}
+
+/// 只实现了进度回调的决定器，回调保存在共享位置，每次决定时以所选走法调用一次
+struct CallbackDecider {
+    inner: RandomDecider,
+    callback: Arc<Mutex<Option<ProgressCallback>>>,
+}
+
+impl Decider for CallbackDecider {
+    /// 随机走子并以所选走法调用进度回调
+    fn make_decision(&mut self, board: &Board, side: Side) -> Decision {
+        let decision = self.inner.make_decision(board, side);
+        if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+            callback.call(&SearchResult {
+                best_move: decision.get_move(),
+                ..SearchResult::default()
+            });
+        }
+        decision
+    }
+
+    /// 将进度回调存入共享位置
+    fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        *self.callback.lock().unwrap() = Some(callback);
+    }
+}
+
+/// 测试：有观察者时游戏不改动决定器自己的进度回调，另行收集搜索信息
+#[test]
+fn game_keeps_progress_callback() {
+    let red_calls = Arc::new(AtomicUsize::new(0));
+    let counter = red_calls.clone();
+    let callback = Arc::new(Mutex::new(None));
+    let mut red = CallbackDecider {
+        inner: RandomDecider::new(),
+        callback: callback.clone(),
+    };
+    red.set_progress_callback(ProgressCallback::new(move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }));
+    let black_calls = Arc::new(AtomicUsize::new(0));
+    let counter = black_calls.clone();
+    let mut black = MaxMinDecider::with_limits(SimpleEvaluator::new(), SearchLimits::depth(2));
+    black.set_progress_callback(ProgressCallback::new(move |_| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }));
+    let mut game = Game::new(red, black);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    game.add_observer(RecordingObserver {
+        events: events.clone(),
+    });
+    let mut red_moves = 0;
+    for _ in 0..2 {
+        let outcome = game.go(&mut Board::new());
+        red_moves += outcome.move_count.div_ceil(2) as usize;
+        // 只实现了进度回调的一方每步都调用自己的回调，不向观察者报告
+        assert!(callback.lock().unwrap().is_some());
+        assert_eq!(red_calls.load(Ordering::Relaxed), red_moves);
+        let events = events.lock().unwrap();
+        let count = |event: &str| events.iter().filter(|e| e.as_str() == event).count();
+        assert_eq!(count("info Red"), 0);
+        // 另一方自己的回调和观察者都收到每轮迭代的搜索信息
+        assert!(count("info Black") > 0);
+        assert_eq!(black_calls.load(Ordering::Relaxed), count("info Black"));
+    }
+}
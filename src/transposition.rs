@@ -0,0 +1,259 @@
+/*
+ * 模块 transposition，搜索用的置换表。
+ * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
+ */
+
+use crate::{
+    board::{Move, Piece, PieceKind, Side},
+    score::Score,
+    square::Square,
+};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// 置换表
+///
+/// 固定大小，按局面哈希直接映射到槽位。每个槽位由两个原子整数组成，
+/// 键中混入数据以检测并发写入造成的撕裂，因此可以不加锁地在多个线程间共享。
+pub struct TranspositionTable {
+    entries: Vec<Slot>,
+    generation: AtomicU8,
+}
+
+/// 置换表条目
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableEntry {
+    /// 搜索深度
+    pub depth: u32,
+    /// 分数的类型
+    pub bound: Bound,
+    /// 分数
+    pub score: Score,
+    /// 最佳走法
+    pub best_move: Option<Move>,
+}
+
+/// 分数的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// 精确值
+    Exact,
+    /// 下界，即发生了 beta 截断
+    Lower,
+    /// 上界，即所有走法都没有超过 alpha
+    Upper,
+}
+
+/// 槽位
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// 槽位大小
+const SLOT_SIZE: usize = std::mem::size_of::<Slot>();
+
+/// 黑方走子时混入局面哈希的值
+const BLACK_TO_MOVE_KEY: u64 = 0x8a5c_d789_635d_2dff;
+
+impl TranspositionTable {
+    /// 清空置换表，通常在两盘棋之间调用
+    pub fn clear(&self) {
+        for slot in &self.entries {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// 查询是否没有槽位，构造时至少分配一个槽位，因此总是 `false`
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 由局面哈希和走子方得到置换表键
+    pub fn key(board_hash: u64, side: Side) -> u64 {
+        match side {
+            Side::Red => board_hash,
+            Side::Black => board_hash ^ BLACK_TO_MOVE_KEY,
+        }
+    }
+
+    /// 获取槽位个数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 按占用内存构造，单位为 MiB，至少包含一个槽位
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let count = (megabytes * 1024 * 1024 / SLOT_SIZE).max(1);
+        TranspositionTable {
+            entries: (0..count).map(|_| Slot::default()).collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// 开始新的一次搜索，此后旧的条目会被优先替换
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 查询条目
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        let slot = &self.entries[self.index(key)];
+        let data = slot.data.load(Ordering::Relaxed);
+        if data != 0 && slot.key.load(Ordering::Relaxed) ^ data == key {
+            Some(unpack(data))
+        } else {
+            None
+        }
+    }
+
+    /// 写入条目
+    ///
+    /// 替换策略：空槽位、之前搜索留下的条目和同一局面的条目总是被替换，
+    /// 本次搜索中其他局面的条目只有在新条目深度不小于它时才被替换。
+    pub fn store(&self, key: u64, mut entry: TableEntry) {
+        let slot = &self.entries[self.index(key)];
+        let generation = self.generation.load(Ordering::Relaxed) & GENERATION_MASK as u8;
+        let old_data = slot.data.load(Ordering::Relaxed);
+        if old_data != 0 {
+            let old_entry = unpack(old_data);
+            if slot.key.load(Ordering::Relaxed) ^ old_data == key {
+                // 同一局面没有新的最佳走法时保留旧的
+                if entry.best_move.is_none() {
+                    entry.best_move = old_entry.best_move;
+                }
+            } else if ((old_data >> GENERATION_SHIFT) & GENERATION_MASK) as u8 == generation
+                && entry.depth < old_entry.depth
+            {
+                return;
+            }
+        }
+        let data = pack(&entry, generation);
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    /// 获取键对应的槽位下标
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+}
+
+impl Clone for TranspositionTable {
+    /// 复制，包括所有条目
+    fn clone(&self) -> TranspositionTable {
+        TranspositionTable {
+            entries: self
+                .entries
+                .iter()
+                .map(|slot| Slot {
+                    key: AtomicU64::new(slot.key.load(Ordering::Relaxed)),
+                    data: AtomicU64::new(slot.data.load(Ordering::Relaxed)),
+                })
+                .collect(),
+            generation: AtomicU8::new(self.generation.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl fmt::Debug for TranspositionTable {
+    /// 只输出大小，不输出条目
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/*
+ * 数据编码，从低位到高位依次为：
+ * 分数 16 位，深度 8 位，分数类型 2 位，走法 21 位（有无 1 位，起点 7 位，终点 7 位，
+ * 变为的棋子 6 位），代数 8 位。
+ * 分数类型从 1 开始编号，因此有效数据一定非零。
+ */
+
+const DEPTH_SHIFT: u32 = 16;
+const BOUND_SHIFT: u32 = 24;
+const MOVE_SHIFT: u32 = 26;
+const GENERATION_SHIFT: u32 = 47;
+const GENERATION_MASK: u64 = 0xff;
+
+/// 编码条目
+fn pack(entry: &TableEntry, generation: u8) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    let mov = match entry.best_move {
+        Some(mov) => {
+            let turn_into = match mov.turn_into {
+                Some(piece) => ((piece.kind as u64) << 1) | piece.side as u64,
+                None => 0,
+            };
+            1 | (mov.pos_from.index() as u64) << 1
+                | (mov.pos_to.index() as u64) << 8
+                | turn_into << 15
+        }
+        None => 0,
+    };
+    entry.score.get_value() as u16 as u64
+        | (entry.depth.min(255) as u64) << DEPTH_SHIFT
+        | bound << BOUND_SHIFT
+        | mov << MOVE_SHIFT
+        | (generation as u64 & GENERATION_MASK) << GENERATION_SHIFT
+}
+
+/// 解码条目
+fn unpack(data: u64) -> TableEntry {
+    let bound = match (data >> BOUND_SHIFT) & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let mov = (data >> MOVE_SHIFT) & ((1 << 21) - 1);
+    let best_move = match (
+        mov & 1,
+        Square::from_index(((mov >> 1) & 0x7f) as usize),
+        Square::from_index(((mov >> 8) & 0x7f) as usize),
+    ) {
+        (1, Some(pos_from), Some(pos_to)) => Some(Move::new(
+            pos_from,
+            pos_to,
+            piece_from_code((mov >> 15) & 0x3f),
+        )),
+        _ => None,
+    };
+    TableEntry {
+        depth: ((data >> DEPTH_SHIFT) & 0xff) as u32,
+        bound,
+        score: Score::from_raw(data as u16 as i16 as i32),
+        best_move,
+    }
+}
+
+/// 由编码得到棋子
+fn piece_from_code(code: u64) -> Option<Piece> {
+    let kind = match code >> 1 {
+        1 => PieceKind::帥,
+        2 => PieceKind::車,
+        3 => PieceKind::馬,
+        4 => PieceKind::炮,
+        5 => PieceKind::相,
+        6 => PieceKind::仕,
+        7 => PieceKind::中兵,
+        8 => PieceKind::濟兵,
+        9 => PieceKind::庶兵,
+        10 => PieceKind::底兵,
+        _ => return None,
+    };
+    let side = if code & 1 == 0 {
+        Side::Red
+    } else {
+        Side::Black
+    };
+    Some(Piece::new(kind, side))
+}
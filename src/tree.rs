@@ -0,0 +1,261 @@
+/*
+ * 模块 tree，用于打谱和复盘的变着树。
+ * 本文件属于 libdonyeh，使用需遵守 LGPL-3.0 协议。
+ */
+
+use crate::board::{Board, Move, Side};
+use std::fmt;
+
+/// 变着树
+///
+/// 根节点对应起始局面，其余每个节点对应一步走子。每个节点的第一个子节点为主线，
+/// 其余子节点为变着。删除节点后其编号不会被重新使用，以已删除节点的编号查询得到 `None`。
+#[derive(Debug, Clone)]
+pub struct GameTree {
+    board: Board,
+    side: Side,
+    nodes: Vec<Option<GameNode>>,
+}
+
+/// 变着树节点
+#[derive(Debug, Clone)]
+pub struct GameNode {
+    mov: Option<Move>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    /// 注释
+    pub comments: Vec<String>,
+    /// NAG 注解编号，如 1 表示“!”，2 表示“?”
+    pub nags: Vec<u8>,
+}
+
+/// 变着树节点编号
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// 编辑变着树时的错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeError {
+    /// 走子不合法或不是轮到走子的一方
+    IllegalMove,
+    /// 节点不存在或已被删除
+    NodeNotFound,
+    /// 根节点不能删除
+    RootNode,
+}
+
+/// 深度优先遍历迭代器，见 [`GameTree::traverse`]
+#[derive(Debug, Clone)]
+pub struct Traverse<'a> {
+    tree: &'a GameTree,
+    stack: Vec<NodeId>,
+}
+
+impl GameTree {
+    /// 在某节点之后添加一步，返回新节点；若该步已存在则返回已有节点
+    ///
+    /// 走子不合法、不是轮到走子的一方或节点不存在时返回 `Err`。
+    pub fn add_variation(&mut self, parent: NodeId, mov: &Move) -> Result<NodeId, TreeError> {
+        let board = self.get_board_at(parent).ok_or(TreeError::NodeNotFound)?;
+        let side = self
+            .get_side_to_move(parent)
+            .ok_or(TreeError::NodeNotFound)?;
+        if board.game_finished()
+            || !board.check_move(mov)
+            || !board.crossing_occupied_by_side(mov.pos_from, side)
+        {
+            return Err(TreeError::IllegalMove);
+        }
+        let node = self.get_node(parent).ok_or(TreeError::NodeNotFound)?;
+        for &child in &node.children {
+            if self.get_node(child).and_then(|child| child.mov) == Some(*mov) {
+                return Ok(child);
+            }
+        }
+        self.nodes.push(Some(GameNode {
+            mov: Some(*mov),
+            parent: Some(parent),
+            children: Vec::new(),
+            comments: Vec::new(),
+            nags: Vec::new(),
+        }));
+        let id = NodeId(self.nodes.len() - 1);
+        self.node_mut(parent).children.push(id);
+        Ok(id)
+    }
+
+    /// 删除某节点及其所有后续变着，根节点不能删除
+    pub fn delete_branch(&mut self, id: NodeId) -> Result<(), TreeError> {
+        let parent = self
+            .get_node(id)
+            .ok_or(TreeError::NodeNotFound)?
+            .parent
+            .ok_or(TreeError::RootNode)?;
+        self.node_mut(parent).children.retain(|&child| child != id);
+        let removed: Vec<NodeId> = self.traverse(id).collect();
+        for removed_id in removed {
+            self.nodes[removed_id.0] = None;
+        }
+        Ok(())
+    }
+
+    /// 获取某节点对应的局面
+    pub fn get_board_at(&self, id: NodeId) -> Option<Board> {
+        let mut board = self.board.clone();
+        for mov in self.get_moves_to(id)? {
+            board.apply_move_unchecked(&mov);
+        }
+        Some(board)
+    }
+
+    /// 获取主线，即从根节点开始不断取第一个子节点得到的节点序列（不含根节点）
+    pub fn get_main_line(&self) -> Vec<NodeId> {
+        let mut ret = Vec::new();
+        let mut current = self.get_root();
+        while let Some(&child) = self.node(current).children.first() {
+            ret.push(child);
+            current = child;
+        }
+        ret
+    }
+
+    /// 获取从起始局面走到某节点的所有走子
+    pub fn get_moves_to(&self, id: NodeId) -> Option<Vec<Move>> {
+        let mut ret = Vec::new();
+        let mut current = self.get_node(id)?;
+        while let Some(mov) = current.mov {
+            ret.push(mov);
+            current = self.node(current.parent.unwrap());
+        }
+        ret.reverse();
+        Some(ret)
+    }
+
+    /// 获取节点，节点已被删除时返回 `None`
+    pub fn get_node(&self, id: NodeId) -> Option<&GameNode> {
+        self.nodes.get(id.0).and_then(|node| node.as_ref())
+    }
+
+    /// 获取可修改的节点，用于编辑注释和注解
+    pub fn get_node_mut(&mut self, id: NodeId) -> Option<&mut GameNode> {
+        self.nodes.get_mut(id.0).and_then(|node| node.as_mut())
+    }
+
+    /// 获取根节点
+    pub fn get_root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// 获取某节点之后轮到走子的一方
+    pub fn get_side_to_move(&self, id: NodeId) -> Option<Side> {
+        if self.get_moves_to(id)?.len() % 2 == 0 {
+            Some(self.side)
+        } else {
+            Some(self.side.other())
+        }
+    }
+
+    /// 获取起始局面
+    pub fn get_start_board(&self) -> &Board {
+        &self.board
+    }
+
+    /// 获取起始局面轮到走子的一方
+    pub fn get_start_side(&self) -> Side {
+        self.side
+    }
+
+    /// 以某局面为起始局面、`side` 为起始局面轮到走子的一方构造
+    pub fn new(board: Board, side: Side) -> GameTree {
+        GameTree {
+            board,
+            side,
+            nodes: vec![Some(GameNode {
+                mov: None,
+                parent: None,
+                children: Vec::new(),
+                comments: Vec::new(),
+                nags: Vec::new(),
+            })],
+        }
+    }
+
+    /// 获取节点，节点必须存在
+    fn node(&self, id: NodeId) -> &GameNode {
+        self.get_node(id).unwrap()
+    }
+
+    /// 获取可修改的节点，节点必须存在
+    fn node_mut(&mut self, id: NodeId) -> &mut GameNode {
+        self.get_node_mut(id).unwrap()
+    }
+
+    /// 将某节点所在的变着提升为主线
+    ///
+    /// 从该节点到根节点路径上的每个节点都会成为其父节点的第一个子节点。
+    pub fn promote_variation(&mut self, id: NodeId) -> Result<(), TreeError> {
+        self.get_node(id).ok_or(TreeError::NodeNotFound)?;
+        let mut current = id;
+        while let Some(parent) = self.node(current).parent {
+            let children = &mut self.node_mut(parent).children;
+            let index = children.iter().position(|&child| child == current).unwrap();
+            let child = children.remove(index);
+            children.insert(0, child);
+            current = parent;
+        }
+        Ok(())
+    }
+
+    /// 以深度优先顺序遍历某节点及其所有后续节点，主线优先
+    pub fn traverse(&self, id: NodeId) -> Traverse<'_> {
+        Traverse {
+            tree: self,
+            stack: match self.get_node(id) {
+                Some(_) => vec![id],
+                None => Vec::new(),
+            },
+        }
+    }
+}
+
+impl GameNode {
+    /// 获取子节点，第一个为主线
+    pub fn get_children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    /// 获取该节点对应的走子，根节点为 `None`
+    pub fn get_move(&self) -> Option<&Move> {
+        self.mov.as_ref()
+    }
+
+    /// 获取父节点，根节点为 `None`
+    pub fn get_parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+}
+
+impl fmt::Display for TreeError {
+    /// 输出错误信息
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TreeError::IllegalMove => "走子不合法",
+            TreeError::NodeNotFound => "节点不存在",
+            TreeError::RootNode => "根节点不能删除",
+        })
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+impl Iterator for Traverse<'_> {
+    type Item = NodeId;
+
+    /// 获取下一个节点
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.stack.pop()?;
+        self.stack
+            .extend(self.tree.node(id).children.iter().rev().copied());
+        Some(id)
+    }
+}